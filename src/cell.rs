@@ -1,14 +1,30 @@
+use rand::RngCore;
 use slotmap::{new_key_type, DenseSlotMap};
-use std::collections::HashSet;
+use std::collections::{HashSet, VecDeque};
 
 use crate::gene::{Gene, GeneKey};
+use crate::grid::Direction;
 use crate::lookup;
+use crate::organelle::{Connection, Organelle};
+use crate::reaction::Pool;
 
 new_key_type! {pub struct CellKey; }
 
 pub struct Cell {
     gene_lookup: lookup::Lookup<GeneKey>,
     genes: HashSet<GeneKey>,
+    barrier: i64,
+    pub pool: Pool,
+    organelle_lookup: lookup::Lookup<usize>,
+    pub organelles: Vec<Organelle>,
+    // messages sent by neighboring cells, shared by all genes
+    pub messages: VecDeque<u32>,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Cell {
@@ -16,6 +32,11 @@ impl Cell {
         Cell {
             gene_lookup: lookup::Lookup::new(),
             genes: HashSet::new(),
+            barrier: 0,
+            pool: Pool::new(),
+            organelle_lookup: lookup::Lookup::new(),
+            organelles: vec![],
+            messages: VecDeque::new(),
         }
     }
 
@@ -24,11 +45,96 @@ impl Cell {
         self.genes.insert(gene_key);
     }
 
-    pub fn lookup_gene_id(&self, genes: &DenseSlotMap<GeneKey, Gene>, coordinates: u32) -> u32 {
-        genes[*self.gene_lookup.find(coordinates)].id
+    pub fn remove_gene(&mut self, gene_key: GeneKey) {
+        self.gene_lookup.remove(&gene_key).unwrap();
+        self.genes.remove(&gene_key);
+    }
+
+    pub fn lookup_gene_key(&self, coordinates: u32) -> Option<GeneKey> {
+        self.gene_lookup.find(coordinates).copied()
+    }
+
+    pub fn lookup_gene_id(
+        &self,
+        genes: &DenseSlotMap<GeneKey, Gene>,
+        coordinates: u32,
+    ) -> Option<u32> {
+        self.lookup_gene_key(coordinates)
+            .map(|gene_key| genes[gene_key].id)
+    }
+
+    pub fn gene_keys(&self) -> impl Iterator<Item = GeneKey> + '_ {
+        self.genes.iter().copied()
     }
 
     pub fn has_gene(&self, gene_key: GeneKey) -> bool {
         self.genes.contains(&gene_key)
     }
+
+    // returns the org_id of the new organelle
+    pub fn add_organelle(&mut self, coordinates: u32, organelle: Organelle) -> usize {
+        let index = self.organelles.len();
+        self.organelle_lookup.add(coordinates, index).unwrap();
+        self.organelles.push(organelle);
+        index
+    }
+
+    pub fn lookup_organelle_id(&self, coordinates: u32) -> Option<usize> {
+        self.organelle_lookup.find(coordinates).copied()
+    }
+
+    // the connection organelle on the side of direction
+    pub fn connection(&self, direction: Direction) -> Option<(usize, &Connection)> {
+        self.organelles
+            .iter()
+            .enumerate()
+            .find_map(|(index, organelle)| match organelle {
+                Organelle::Connection(connection) if connection.direction == direction => {
+                    Some((index, connection))
+                }
+                _ => None,
+            })
+    }
+
+    pub fn connection_mut(&mut self, direction: Direction) -> Option<&mut Connection> {
+        self.organelles
+            .iter_mut()
+            .find_map(|organelle| match organelle {
+                Organelle::Connection(connection) if connection.direction == direction => {
+                    Some(connection)
+                }
+                _ => None,
+            })
+    }
+
+    pub fn organelle_read(&mut self, index: usize, rng: &mut dyn RngCore) -> Option<u32> {
+        self.organelles
+            .get_mut(index)
+            .map(|organelle| organelle.read(rng))
+    }
+
+    pub fn organelle_write(
+        &mut self,
+        index: usize,
+        value: u32,
+        light: f64,
+        rng: &mut dyn RngCore,
+    ) -> Option<usize> {
+        let pool = &mut self.pool;
+        self.organelles
+            .get_mut(index)
+            .map(|organelle| organelle.write(value, pool, light, rng))
+    }
+
+    pub fn barrier(&self) -> i64 {
+        self.barrier
+    }
+
+    pub fn set_barrier(&mut self, barrier: i64) {
+        self.barrier = barrier;
+    }
+
+    pub fn is_barrier_closed(&self) -> bool {
+        self.barrier > 0
+    }
 }
@@ -1,21 +1,92 @@
 use slotmap::new_key_type;
 
+use crate::lookup::Lookup;
+use crate::processor::{CompiledInstruction, Instruction};
+
 new_key_type! { pub struct GeneKey; }
 
 pub struct Gene {
     pub id: u32,
-    pub code: Vec<u32>,
+    code: Vec<u32>,
+    // the code compiled for execution, kept in step with code
+    compiled: Vec<CompiledInstruction>,
+    // how active the gene is
+    pub activation: u32,
 }
 
 impl Gene {
-    pub fn new(id: u32, code: &[u32]) -> Gene {
-        return Gene {
-            id: id,
+    pub fn new(id: u32, code: &[u32], instruction_lookup: &Lookup<Instruction>) -> Gene {
+        Gene {
+            id,
             code: code.to_vec(),
-        };
+            compiled: code
+                .iter()
+                .map(|&value| CompiledInstruction::compile(value, instruction_lookup))
+                .collect(),
+            activation: 1,
+        }
+    }
+
+    // add a value to the end of the gene, compiling it along the way
+    pub fn write(&mut self, value: u32, instruction_lookup: &Lookup<Instruction>) {
+        self.code.push(value);
+        self.compiled
+            .push(CompiledInstruction::compile(value, instruction_lookup));
+    }
+
+    pub fn code(&self) -> &[u32] {
+        &self.code
+    }
+
+    pub fn compiled(&self, index: usize) -> CompiledInstruction {
+        self.compiled[index]
     }
 
     pub fn coordinates(&self) -> u32 {
         self.code[0] & 0xFFFFFF
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::{instruction_lookup, ProcessorInstruction};
+    use crate::stack;
+
+    #[test]
+    fn test_compile() {
+        let lookup = instruction_lookup();
+        let add = Instruction::StackInstruction(stack::Instruction::Add);
+        let gene = Gene::new(
+            0,
+            &[3, 0x01000000 | add.coordinates(), 0x02000003, 0x03000003],
+            &lookup,
+        );
+        assert_eq!(gene.compiled(0), CompiledInstruction::Number(3));
+        assert_eq!(
+            gene.compiled(1),
+            CompiledInstruction::Instruction(Some(add))
+        );
+        assert_eq!(gene.compiled(2), CompiledInstruction::Call(3));
+        assert_eq!(gene.compiled(3), CompiledInstruction::Noop);
+    }
+
+    #[test]
+    fn test_write() {
+        let lookup = instruction_lookup();
+        let call = Instruction::ProcessorInstruction(ProcessorInstruction::Call);
+        let mut gene = Gene::new(0, &[3], &lookup);
+        gene.write(0x01000000 | call.coordinates(), &lookup);
+        assert_eq!(gene.code(), [3, 0x01000000 | call.coordinates()]);
+        assert_eq!(
+            gene.compiled(1),
+            CompiledInstruction::Instruction(Some(call))
+        );
+    }
+
+    #[test]
+    fn test_compile_without_instructions() {
+        let gene = Gene::new(0, &[0x01000000], &Lookup::new());
+        assert_eq!(gene.compiled(0), CompiledInstruction::Instruction(None));
+    }
+}
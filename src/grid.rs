@@ -0,0 +1,403 @@
+use slotmap::SecondaryMap;
+
+use crate::cell::CellKey;
+use crate::reaction::{Molecule, Pool};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Direction {
+    pub fn from_nr(nr: u32) -> Direction {
+        match nr % 4 {
+            0 => Direction::North,
+            1 => Direction::East,
+            2 => Direction::South,
+            _ => Direction::West,
+        }
+    }
+
+    pub fn opposite(&self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::East => Direction::West,
+            Direction::South => Direction::North,
+            Direction::West => Direction::East,
+        }
+    }
+
+    pub fn all() -> [Direction; 4] {
+        [
+            Direction::North,
+            Direction::East,
+            Direction::South,
+            Direction::West,
+        ]
+    }
+}
+
+// The offsets at exactly distance steps away (moving north, east, south or
+// west), from north to south.
+pub fn ring_offsets(distance: usize) -> Vec<(isize, isize)> {
+    let distance = distance as isize;
+    if distance == 0 {
+        return vec![(0, 0)];
+    }
+    let mut offsets = vec![];
+    for dy in -distance..=distance {
+        let dx = distance - dy.abs();
+        offsets.push((-dx, dy));
+        if dx != 0 {
+            offsets.push((dx, dy));
+        }
+    }
+    offsets
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Topology {
+    // the edges wrap around to the other side
+    Toroidal,
+    // there is nothing beyond the edges
+    Bounded,
+}
+
+pub type Position = (usize, usize);
+
+pub struct Grid {
+    pub width: usize,
+    pub height: usize,
+    pub topology: Topology,
+    locations: Vec<Option<CellKey>>,
+    positions: SecondaryMap<CellKey, Position>,
+    environment: Vec<Pool>,
+}
+
+impl Grid {
+    pub fn new(width: usize, height: usize, topology: Topology) -> Grid {
+        Grid {
+            width,
+            height,
+            topology,
+            locations: vec![None; width * height],
+            positions: SecondaryMap::new(),
+            environment: (0..width * height).map(|_| Pool::unbounded()).collect(),
+        }
+    }
+
+    fn index(&self, position: Position) -> usize {
+        let (x, y) = position;
+        y * self.width + x
+    }
+
+    pub fn contains(&self, position: Position) -> bool {
+        let (x, y) = position;
+        x < self.width && y < self.height
+    }
+
+    pub fn neighbor_position(&self, position: Position, direction: Direction) -> Option<Position> {
+        let (dx, dy) = match direction {
+            Direction::North => (0, -1),
+            Direction::East => (1, 0),
+            Direction::South => (0, 1),
+            Direction::West => (-1, 0),
+        };
+        self.offset_position(position, dx, dy)
+    }
+
+    // the position dx, dy away, if it is on the grid
+    pub fn offset_position(&self, position: Position, dx: isize, dy: isize) -> Option<Position> {
+        let (x, y) = position;
+        let (x, y) = (x as isize + dx, y as isize + dy);
+        let (width, height) = (self.width as isize, self.height as isize);
+        match self.topology {
+            Topology::Toroidal => {
+                Some((x.rem_euclid(width) as usize, y.rem_euclid(height) as usize))
+            }
+            Topology::Bounded => {
+                if x < 0 || y < 0 || x >= width || y >= height {
+                    None
+                } else {
+                    Some((x as usize, y as usize))
+                }
+            }
+        }
+    }
+
+    pub fn get(&self, position: Position) -> Option<CellKey> {
+        if !self.contains(position) {
+            return None;
+        }
+        self.locations[self.index(position)]
+    }
+
+    pub fn position(&self, cell_key: CellKey) -> Option<Position> {
+        self.positions.get(cell_key).copied()
+    }
+
+    pub fn neighbor(&self, cell_key: CellKey, direction: Direction) -> Option<CellKey> {
+        self.position(cell_key)
+            .and_then(|position| self.neighbor_position(position, direction))
+            .and_then(|position| self.get(position))
+    }
+
+    pub fn is_free(&self, position: Position) -> bool {
+        self.contains(position) && self.get(position).is_none()
+    }
+
+    pub fn free_position(&self) -> Option<Position> {
+        self.locations
+            .iter()
+            .position(|location| location.is_none())
+            .map(|index| (index % self.width, index / self.width))
+    }
+
+    // the molecules in a location outside of any cell
+    pub fn environment(&self, position: Position) -> &Pool {
+        &self.environment[self.index(position)]
+    }
+
+    pub fn environment_mut(&mut self, position: Position) -> &mut Pool {
+        let index = self.index(position);
+        &mut self.environment[index]
+    }
+
+    // Each location spreads rate of its amount of molecule evenly over the
+    // neighboring locations. Amounts are whole, so small amounts stay put.
+    // The rate is kept within 0.0 to 1.0, as a location cannot give away
+    // more than it has.
+    pub fn diffuse(&mut self, molecule: Molecule, rate: f64) {
+        let rate = rate.clamp(0.0, 1.0);
+        let mut deltas = vec![0; self.environment.len()];
+        for index in 0..self.environment.len() {
+            let position = (index % self.width, index / self.width);
+            let amount = self.environment[index].amount(molecule);
+            let share = (amount as f64 * rate / 4.0) as i64;
+            if share == 0 {
+                continue;
+            }
+            for &direction in Direction::all().iter() {
+                if let Some(neighbor_position) = self.neighbor_position(position, direction) {
+                    deltas[self.index(neighbor_position)] += share;
+                    deltas[index] -= share;
+                }
+            }
+        }
+        for (pool, delta) in self.environment.iter_mut().zip(deltas) {
+            pool.add(molecule, delta);
+        }
+    }
+
+    pub fn total_environment_amount(&self, molecule: Molecule) -> i64 {
+        self.environment
+            .iter()
+            .map(|pool| pool.amount(molecule))
+            .sum()
+    }
+
+    pub fn insert(&mut self, cell_key: CellKey, position: Position) -> bool {
+        if !self.is_free(position) || self.positions.contains_key(cell_key) {
+            return false;
+        }
+        let index = self.index(position);
+        self.locations[index] = Some(cell_key);
+        self.positions.insert(cell_key, position);
+        true
+    }
+
+    pub fn remove(&mut self, cell_key: CellKey) -> Option<Position> {
+        let position = self.positions.remove(cell_key)?;
+        let index = self.index(position);
+        self.locations[index] = None;
+        Some(position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slotmap::DenseSlotMap;
+
+    #[test]
+    fn test_neighbor_position() {
+        let grid = Grid::new(3, 3, Topology::Bounded);
+        assert_eq!(
+            grid.neighbor_position((1, 1), Direction::North),
+            Some((1, 0))
+        );
+        assert_eq!(
+            grid.neighbor_position((1, 1), Direction::East),
+            Some((2, 1))
+        );
+        assert_eq!(
+            grid.neighbor_position((1, 1), Direction::South),
+            Some((1, 2))
+        );
+        assert_eq!(
+            grid.neighbor_position((1, 1), Direction::West),
+            Some((0, 1))
+        );
+    }
+
+    #[test]
+    fn test_neighbor_position_bounded() {
+        let grid = Grid::new(3, 3, Topology::Bounded);
+        assert_eq!(grid.neighbor_position((0, 0), Direction::North), None);
+        assert_eq!(grid.neighbor_position((0, 0), Direction::West), None);
+        assert_eq!(grid.neighbor_position((2, 2), Direction::East), None);
+        assert_eq!(grid.neighbor_position((2, 2), Direction::South), None);
+    }
+
+    #[test]
+    fn test_neighbor_position_toroidal() {
+        let grid = Grid::new(3, 3, Topology::Toroidal);
+        assert_eq!(
+            grid.neighbor_position((0, 0), Direction::North),
+            Some((0, 2))
+        );
+        assert_eq!(
+            grid.neighbor_position((0, 0), Direction::West),
+            Some((2, 0))
+        );
+        assert_eq!(
+            grid.neighbor_position((2, 2), Direction::East),
+            Some((0, 2))
+        );
+        assert_eq!(
+            grid.neighbor_position((2, 2), Direction::South),
+            Some((2, 0))
+        );
+    }
+
+    #[test]
+    fn test_insert_and_neighbor() {
+        let mut keys = DenseSlotMap::<CellKey, ()>::with_key();
+        let a = keys.insert(());
+        let b = keys.insert(());
+        let mut grid = Grid::new(3, 3, Topology::Bounded);
+        assert!(grid.insert(a, (1, 1)));
+        assert!(grid.insert(b, (2, 1)));
+        assert_eq!(grid.neighbor(a, Direction::East), Some(b));
+        assert_eq!(grid.neighbor(b, Direction::West), Some(a));
+        assert_eq!(grid.neighbor(a, Direction::North), None);
+    }
+
+    #[test]
+    fn test_insert_occupied() {
+        let mut keys = DenseSlotMap::<CellKey, ()>::with_key();
+        let a = keys.insert(());
+        let b = keys.insert(());
+        let mut grid = Grid::new(3, 3, Topology::Bounded);
+        assert!(grid.insert(a, (1, 1)));
+        assert!(!grid.insert(b, (1, 1)));
+        assert!(!grid.insert(b, (3, 1)));
+        assert_eq!(grid.get((1, 1)), Some(a));
+        assert_eq!(grid.position(b), None);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut keys = DenseSlotMap::<CellKey, ()>::with_key();
+        let a = keys.insert(());
+        let mut grid = Grid::new(3, 3, Topology::Bounded);
+        grid.insert(a, (1, 1));
+        assert_eq!(grid.remove(a), Some((1, 1)));
+        assert_eq!(grid.get((1, 1)), None);
+        assert_eq!(grid.position(a), None);
+        assert!(grid.is_free((1, 1)));
+    }
+
+    #[test]
+    fn test_free_position() {
+        let mut keys = DenseSlotMap::<CellKey, ()>::with_key();
+        let a = keys.insert(());
+        let b = keys.insert(());
+        let mut grid = Grid::new(2, 1, Topology::Bounded);
+        assert_eq!(grid.free_position(), Some((0, 0)));
+        grid.insert(a, (0, 0));
+        assert_eq!(grid.free_position(), Some((1, 0)));
+        grid.insert(b, (1, 0));
+        assert_eq!(grid.free_position(), None);
+    }
+
+    #[test]
+    fn test_diffuse() {
+        let mut grid = Grid::new(3, 3, Topology::Bounded);
+        grid.environment_mut((1, 1)).set_amount(Molecule::G, 100);
+        grid.diffuse(Molecule::G, 0.5);
+        assert_eq!(grid.environment((1, 1)).amount(Molecule::G), 52);
+        assert_eq!(grid.environment((1, 0)).amount(Molecule::G), 12);
+        assert_eq!(grid.environment((2, 1)).amount(Molecule::G), 12);
+        assert_eq!(grid.environment((0, 0)).amount(Molecule::G), 0);
+        assert_eq!(grid.total_environment_amount(Molecule::G), 100);
+    }
+
+    #[test]
+    fn test_diffuse_conserves_mass() {
+        for &topology in [Topology::Bounded, Topology::Toroidal].iter() {
+            let mut grid = Grid::new(5, 4, topology);
+            grid.environment_mut((0, 0)).set_amount(Molecule::G, 1000);
+            grid.environment_mut((4, 3)).set_amount(Molecule::G, 333);
+            grid.environment_mut((2, 1)).set_amount(Molecule::O, 77);
+            for _ in 0..50 {
+                grid.diffuse(Molecule::G, 0.3);
+                grid.diffuse(Molecule::O, 1.0);
+            }
+            assert_eq!(grid.total_environment_amount(Molecule::G), 1333);
+            assert_eq!(grid.total_environment_amount(Molecule::O), 77);
+            assert!(grid.environment((4, 0)).amount(Molecule::G) > 0);
+        }
+    }
+
+    #[test]
+    fn test_diffuse_rate_clamped() {
+        for &rate in [1.0, 3.0, -1.0].iter() {
+            let mut grid = Grid::new(3, 3, Topology::Toroidal);
+            grid.environment_mut((1, 1)).set_amount(Molecule::G, 100);
+            grid.environment_mut((0, 2)).set_amount(Molecule::G, 7);
+            for _ in 0..10 {
+                grid.diffuse(Molecule::G, rate);
+            }
+            assert_eq!(grid.total_environment_amount(Molecule::G), 107);
+        }
+        // a rate above 1.0 spreads everything, and no more
+        let mut grid = Grid::new(3, 3, Topology::Toroidal);
+        grid.environment_mut((1, 1)).set_amount(Molecule::G, 100);
+        grid.diffuse(Molecule::G, 3.0);
+        assert_eq!(grid.environment((1, 1)).amount(Molecule::G), 0);
+        assert_eq!(grid.environment((1, 0)).amount(Molecule::G), 25);
+    }
+
+    #[test]
+    fn test_diffuse_edge() {
+        let mut grid = Grid::new(2, 1, Topology::Bounded);
+        grid.environment_mut((0, 0)).set_amount(Molecule::G, 40);
+        grid.diffuse(Molecule::G, 1.0);
+        // only one neighbor to spread to
+        assert_eq!(grid.environment((0, 0)).amount(Molecule::G), 30);
+        assert_eq!(grid.environment((1, 0)).amount(Molecule::G), 10);
+    }
+
+    #[test]
+    fn test_ring_offsets() {
+        assert_eq!(ring_offsets(0), [(0, 0)]);
+        assert_eq!(ring_offsets(1), [(0, -1), (-1, 0), (1, 0), (0, 1)]);
+        assert_eq!(ring_offsets(2).len(), 8);
+        assert!(ring_offsets(3)
+            .iter()
+            .all(|(dx, dy)| dx.abs() + dy.abs() == 3));
+    }
+
+    #[test]
+    fn test_offset_position() {
+        let grid = Grid::new(3, 3, Topology::Bounded);
+        assert_eq!(grid.offset_position((1, 1), -1, 1), Some((0, 2)));
+        assert_eq!(grid.offset_position((1, 1), -2, 0), None);
+        let grid = Grid::new(3, 3, Topology::Toroidal);
+        assert_eq!(grid.offset_position((1, 1), -2, 0), Some((2, 1)));
+    }
+}
@@ -1,11 +1,14 @@
-#[macro_use]
-
 mod cell;
 mod gene;
+pub mod grid;
+pub mod light;
 pub mod lookup;
-pub mod port; // not really public
+pub mod organelle;
+pub mod port;
 pub mod processor;
-// pub mod reaction; // not really public
+pub mod reaction;
+pub mod scent;
 pub mod stack;
-mod triplet;
+pub mod task;
+pub mod triplet;
 pub mod world;
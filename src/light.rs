@@ -0,0 +1,90 @@
+// The light that falls on the grid, which drives photosynthesis.
+use crate::grid::Position;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Light {
+    // the same everywhere, all the time
+    Constant(f64),
+    // changes evenly from the west edge to the east edge
+    Gradient { west: f64, east: f64 },
+    // light for day ticks, followed by darkness for night ticks
+    DayNight { day: u64, night: u64, light: f64 },
+}
+
+impl Light {
+    // the light at a position on a grid of width, from 0.0 (dark) to 1.0
+    pub fn at(&self, position: Position, width: usize, tick: u64) -> f64 {
+        let light = match *self {
+            Light::Constant(light) => light,
+            Light::Gradient { west, east } => {
+                if width <= 1 {
+                    west
+                } else {
+                    let (x, _) = position;
+                    west + (east - west) * x as f64 / (width - 1) as f64
+                }
+            }
+            Light::DayNight { day, night, light } => {
+                // without day or night it is always dark
+                let cycle = day.saturating_add(night);
+                if cycle > 0 && tick % cycle < day {
+                    light
+                } else {
+                    0.0
+                }
+            }
+        };
+        light.clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constant() {
+        let light = Light::Constant(0.5);
+        assert_eq!(light.at((0, 0), 10, 0), 0.5);
+        assert_eq!(light.at((9, 3), 10, 100), 0.5);
+    }
+
+    #[test]
+    fn test_gradient() {
+        let light = Light::Gradient {
+            west: 0.0,
+            east: 1.0,
+        };
+        assert_eq!(light.at((0, 0), 5, 0), 0.0);
+        assert_eq!(light.at((2, 7), 5, 0), 0.5);
+        assert_eq!(light.at((4, 0), 5, 0), 1.0);
+    }
+
+    #[test]
+    fn test_day_night() {
+        let light = Light::DayNight {
+            day: 3,
+            night: 2,
+            light: 0.8,
+        };
+        let lights: Vec<f64> = (0..6).map(|tick| light.at((0, 0), 1, tick)).collect();
+        assert_eq!(lights, [0.8, 0.8, 0.8, 0.0, 0.0, 0.8]);
+    }
+
+    #[test]
+    fn test_day_night_without_cycle() {
+        let light = Light::DayNight {
+            day: 0,
+            night: 0,
+            light: 0.8,
+        };
+        assert_eq!(light.at((0, 0), 1, 0), 0.0);
+        assert_eq!(light.at((0, 0), 1, 7), 0.0);
+    }
+
+    #[test]
+    fn test_clamped() {
+        assert_eq!(Light::Constant(2.0).at((0, 0), 1, 0), 1.0);
+        assert_eq!(Light::Constant(-1.0).at((0, 0), 1, 0), 0.0);
+    }
+}
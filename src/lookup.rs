@@ -1,39 +1,110 @@
 use kdtree::distance::squared_euclidean;
 use kdtree::ErrorKind;
 use kdtree::KdTree;
+use rand::Rng;
 
 pub struct Lookup<T> {
     tree: KdTree<f32, T, [f32; 3]>,
+    // the tree cannot remove items, so we keep track of what we
+    // added in order to rebuild it
+    entries: Vec<(u32, T)>,
 }
 
-impl<T> Lookup<T> {
+impl<T: Clone + PartialEq> Default for Lookup<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone + PartialEq> Lookup<T> {
     pub fn new() -> Lookup<T> {
         Lookup {
             tree: KdTree::new(3),
+            entries: vec![],
         }
     }
 
     pub fn add(&mut self, coordinates: u32, item: T) -> LookupAddResult {
-        self.tree.add(coordinates_to_distance(coordinates), item)?;
+        self.tree
+            .add(coordinates_to_distance(coordinates), item.clone())?;
+        self.entries.push((coordinates, item));
+        Ok(())
+    }
+
+    // The kd-tree cannot remove items, so it is rebuilt from the remaining
+    // entries. This is linear in the amount of entries, so moving genes
+    // out of a cell with many genes is costly.
+    pub fn remove(&mut self, item: &T) -> LookupAddResult {
+        self.entries.retain(|(_, entry)| entry != item);
+        self.tree = KdTree::new(3);
+        for (coordinates, entry) in self.entries.iter() {
+            self.tree
+                .add(coordinates_to_distance(*coordinates), entry.clone())?;
+        }
         Ok(())
     }
 
-    pub fn find(&self, coordinates: u32) -> &T {
-        let v = self
-            .tree
+    pub fn find(&self, coordinates: u32) -> Option<&T> {
+        self.tree
             .nearest(&coordinates_to_distance(coordinates), 1, &squared_euclidean)
-            .unwrap();
-        v[0].1
+            .ok()
+            .and_then(|v| v.first().map(|(_, item)| *item))
+    }
+
+    // find with noise, so that nearby items are found now and then
+    pub fn find_perturbed<R: Rng>(
+        &self,
+        coordinates: u32,
+        perturbation: &Perturbation,
+        rng: &mut R,
+    ) -> Option<&T> {
+        self.find(perturbation.perturb(coordinates, rng))
+    }
+}
+
+// Noise on a lookup. Each coordinate moves by up to amount, and very rarely
+// by up to huge_amount instead.
+#[derive(Debug, Clone, Copy)]
+pub struct Perturbation {
+    pub amount: u8,
+    pub huge_amount: u8,
+    pub huge_probability: f64,
+}
+
+impl Default for Perturbation {
+    fn default() -> Self {
+        Perturbation {
+            amount: 1,
+            huge_amount: 64,
+            huge_probability: 0.001,
+        }
+    }
+}
+
+impl Perturbation {
+    // the bits above the coordinates are left alone
+    pub fn perturb<R: Rng>(&self, coordinates: u32, rng: &mut R) -> u32 {
+        let amount = if rng.gen_bool(self.huge_probability) {
+            self.huge_amount
+        } else {
+            self.amount
+        } as i32;
+        let mut result = coordinates & 0xFF000000;
+        for &shift in [16, 8, 0].iter() {
+            let coordinate = (coordinates >> shift & 0xff) as i32;
+            let coordinate = (coordinate + rng.gen_range(-amount, amount + 1)).clamp(0, 0xff);
+            result |= (coordinate as u32) << shift;
+        }
+        result
     }
 }
 
 fn coordinates_to_distance(nr: u32) -> [f32; 3] {
-    let i = nr as u32;
-    return [
-        (i >> 16 & 0xff) as f32,
-        (i >> 8 & 0xff) as f32,
-        (i & 0xff) as f32,
-    ];
+    [
+        (nr >> 16 & 0xff) as f32,
+        (nr >> 8 & 0xff) as f32,
+        (nr & 0xff) as f32,
+    ]
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -50,6 +121,9 @@ impl From<ErrorKind> for LookupError {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::SeedableRng;
+
+    const SEED: [u8; 16] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
 
     #[derive(Debug, Copy, Clone, PartialEq)]
     struct Item {
@@ -61,8 +135,8 @@ mod tests {
         let mut l = Lookup::<Item>::new();
         let i = Item { c: 0x010101 };
         l.add(i.c, i)?;
-        assert_eq!(l.find(i.c), &i);
-        return Ok(());
+        assert_eq!(l.find(i.c), Some(&i));
+        Ok(())
     }
 
     #[test]
@@ -73,7 +147,93 @@ mod tests {
 
         l.add(i1.c, i1)?;
         l.add(i2.c, i2)?;
-        assert_eq!(l.find(0x020202), &i1);
-        return Ok(());
+        assert_eq!(l.find(0x020202), Some(&i1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_lookup_empty() {
+        let l = Lookup::<Item>::new();
+        assert_eq!(l.find(0x020202), None);
+    }
+
+    #[test]
+    fn test_perturb_nothing() {
+        let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+        let perturbation = Perturbation {
+            amount: 0,
+            huge_amount: 0,
+            huge_probability: 0.0,
+        };
+        assert_eq!(perturbation.perturb(0x01102030, &mut rng), 0x01102030);
+    }
+
+    #[test]
+    fn test_perturb_within_amount() {
+        let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+        let perturbation = Perturbation {
+            amount: 2,
+            huge_amount: 100,
+            huge_probability: 0.0,
+        };
+        for _ in 0..100 {
+            let perturbed = perturbation.perturb(0x0200FF10, &mut rng);
+            assert_eq!(perturbed & 0xFF000000, 0x02000000);
+            // coordinates stay within the byte
+            assert!(perturbed >> 16 & 0xff <= 2);
+            assert!(perturbed >> 8 & 0xff >= 0xFD);
+            let b = perturbed & 0xff;
+            assert!((0x0E..=0x12).contains(&b));
+        }
+    }
+
+    #[test]
+    fn test_perturb_huge() {
+        let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+        let perturbation = Perturbation {
+            amount: 0,
+            huge_amount: 100,
+            huge_probability: 1.0,
+        };
+        let perturbed: Vec<u32> = (0..10)
+            .map(|_| perturbation.perturb(0x808080, &mut rng))
+            .collect();
+        assert!(perturbed.iter().any(|&p| p != 0x808080));
+    }
+
+    #[test]
+    fn test_lookup_find_perturbed() -> LookupAddResult {
+        let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+        let mut l = Lookup::<Item>::new();
+        let i1 = Item { c: 0x010101 };
+        let i2 = Item { c: 0x030303 };
+        l.add(i1.c, i1)?;
+        l.add(i2.c, i2)?;
+        let perturbation = Perturbation {
+            amount: 3,
+            huge_amount: 3,
+            huge_probability: 0.0,
+        };
+        let found: Vec<Item> = (0..100)
+            .map(|_| *l.find_perturbed(0x010101, &perturbation, &mut rng).unwrap())
+            .collect();
+        assert!(found.contains(&i1));
+        assert!(found.contains(&i2));
+        Ok(())
+    }
+
+    #[test]
+    fn test_lookup_remove() -> LookupAddResult {
+        let mut l = Lookup::<Item>::new();
+        let i1 = Item { c: 0x010101 };
+        let i2 = Item { c: 0xF0F0F0 };
+
+        l.add(i1.c, i1)?;
+        l.add(i2.c, i2)?;
+        l.remove(&i1)?;
+        assert_eq!(l.find(0x020202), Some(&i2));
+        l.remove(&i2)?;
+        assert_eq!(l.find(0x020202), None);
+        Ok(())
     }
 }
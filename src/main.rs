@@ -1,13 +1,16 @@
-#[macro_use]
-
 // XXX use pub mod to shut up unused warnings
 pub mod cell;
 pub mod gene;
+pub mod grid;
+pub mod light;
 pub mod lookup;
-// pub mod port;
+pub mod organelle;
+pub mod port;
 pub mod processor;
-// pub mod reaction;
+pub mod reaction;
+pub mod scent;
 pub mod stack;
+pub mod task;
 pub mod triplet;
 pub mod world;
 
@@ -0,0 +1,162 @@
+// Organelles are the equipment a cell uses to interact with the world.
+// Genes talk to them through their ports.
+use rand::RngCore;
+use std::collections::VecDeque;
+
+use crate::cell::CellKey;
+use crate::grid::Direction;
+use crate::port::Catalyst;
+use crate::reaction::Pool;
+
+// What an organelle can be opened or closed for.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Gate {
+    Gene,
+    Chem,
+}
+
+// One side of a connection with a neighboring cell. What the neighbor
+// outputs to its side of the connection ends up as our input.
+pub struct Connection {
+    pub direction: Direction,
+    pub peer: Option<CellKey>,
+    pub gene_open: bool,
+    pub chem_open: bool,
+    pub input: VecDeque<u32>,
+}
+
+impl Connection {
+    pub fn new(direction: Direction) -> Connection {
+        Connection {
+            direction,
+            peer: None,
+            gene_open: true,
+            chem_open: true,
+            input: VecDeque::new(),
+        }
+    }
+
+    pub fn is_open(&self, gate: Gate) -> bool {
+        match gate {
+            Gate::Gene => self.gene_open,
+            Gate::Chem => self.chem_open,
+        }
+    }
+
+    pub fn set_open(&mut self, gate: Gate, open: bool) {
+        match gate {
+            Gate::Gene => self.gene_open = open,
+            Gate::Chem => self.chem_open = open,
+        }
+    }
+}
+
+// Exchanges molecules with the environment at the location of the cell.
+pub struct Environment {
+    pub chem_open: bool,
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Environment {
+    pub fn new() -> Environment {
+        Environment { chem_open: true }
+    }
+}
+
+pub enum Organelle {
+    Catalyst(Catalyst),
+    Connection(Connection),
+    Environment(Environment),
+}
+
+impl Organelle {
+    pub fn read(&mut self, rng: &mut dyn RngCore) -> u32 {
+        match self {
+            Organelle::Catalyst(catalyst) => catalyst.read(rng),
+            Organelle::Connection(connection) => connection.input.pop_front().unwrap_or(0),
+            Organelle::Environment(_) => 0,
+        }
+    }
+
+    pub fn has_input(&self) -> bool {
+        match self {
+            Organelle::Catalyst(catalyst) => catalyst.has_input(),
+            Organelle::Connection(connection) => !connection.input.is_empty(),
+            Organelle::Environment(_) => false,
+        }
+    }
+
+    // returns the amount of reactions that took place. Output to a
+    // connection is delivered by the world, not here.
+    pub fn write(
+        &mut self,
+        value: u32,
+        pool: &mut Pool,
+        light: f64,
+        rng: &mut dyn RngCore,
+    ) -> usize {
+        match self {
+            Organelle::Catalyst(catalyst) => catalyst.write(value, pool, light, rng),
+            Organelle::Connection(_) | Organelle::Environment(_) => 0,
+        }
+    }
+
+    // None if the organelle has no such gate
+    pub fn is_open(&self, gate: Gate) -> Option<bool> {
+        match (self, gate) {
+            (Organelle::Catalyst(catalyst), Gate::Chem) => Some(catalyst.open),
+            (Organelle::Catalyst(_), Gate::Gene) => None,
+            (Organelle::Connection(connection), gate) => Some(connection.is_open(gate)),
+            (Organelle::Environment(environment), Gate::Chem) => Some(environment.chem_open),
+            (Organelle::Environment(_), Gate::Gene) => None,
+        }
+    }
+
+    pub fn set_open(&mut self, gate: Gate, open: bool) {
+        match (self, gate) {
+            (Organelle::Catalyst(catalyst), Gate::Chem) => catalyst.open = open,
+            (Organelle::Catalyst(_), Gate::Gene) => {}
+            (Organelle::Connection(connection), gate) => connection.set_open(gate, open),
+            (Organelle::Environment(environment), Gate::Chem) => environment.chem_open = open,
+            (Organelle::Environment(_), Gate::Gene) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    const SEED: [u8; 16] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+
+    #[test]
+    fn test_connection_input() {
+        let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+        let mut organelle = Organelle::Connection(Connection::new(Direction::North));
+        assert!(!organelle.has_input());
+        if let Organelle::Connection(connection) = &mut organelle {
+            connection.input.push_back(1);
+            connection.input.push_back(2);
+        }
+        assert!(organelle.has_input());
+        assert_eq!(organelle.read(&mut rng), 1);
+        assert_eq!(organelle.read(&mut rng), 2);
+        assert!(!organelle.has_input());
+        assert_eq!(organelle.read(&mut rng), 0);
+    }
+
+    #[test]
+    fn test_connection_gates() {
+        let mut organelle = Organelle::Connection(Connection::new(Direction::North));
+        assert_eq!(organelle.is_open(Gate::Gene), Some(true));
+        organelle.set_open(Gate::Gene, false);
+        assert_eq!(organelle.is_open(Gate::Gene), Some(false));
+        assert_eq!(organelle.is_open(Gate::Chem), Some(true));
+    }
+}
@@ -1,30 +1,49 @@
-pub struct Port<'a> {
+use rand::{Rng, RngCore};
+
+use crate::reaction::{Pool, Reaction};
+use crate::task::Task;
+
+pub struct Port {
     pub input: Vec<u32>,
     pub output: Vec<u32>,
-    pub expected_output: &'a [u32],
+    pub expected_input: Vec<u32>,
+    pub expected_output: Vec<u32>,
+}
+
+impl Default for Port {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl<'a> Port<'a> {
-    pub fn new() -> Port<'a> {
+impl Port {
+    pub fn new() -> Port {
         Port {
             input: vec![],
             output: vec![],
-            expected_output: &[],
+            expected_input: vec![],
+            expected_output: vec![],
         }
     }
 
-    pub fn reset(&mut self, expected_input: &[u32], expected_output: &'a [u32]) {
+    pub fn reset(&mut self, expected_input: &[u32], expected_output: &[u32]) {
+        self.expected_input = expected_input.to_vec();
+        self.expected_output = expected_output.to_vec();
+        self.restart();
+    }
+
+    // start the current problem from the beginning
+    pub fn restart(&mut self) {
         // we reverse the order of the elements so that we
         // can pop them off in the right order
-        let mut cloned_input = expected_input.to_vec();
+        let mut cloned_input = self.expected_input.clone();
         cloned_input.reverse();
         self.input = cloned_input;
         self.output = vec![];
-        self.expected_output = expected_output;
     }
 
     pub fn read(&mut self) -> u32 {
-        if self.input.len() == 0 {
+        if self.input.is_empty() {
             return 0;
         }
         self.input.pop().unwrap()
@@ -37,11 +56,99 @@ impl<'a> Port<'a> {
     pub fn is_done(&self) -> bool {
         self.output.len() >= self.expected_output.len()
     }
+
+    // the amount of output values that match the expected output
+    pub fn correct(&self) -> usize {
+        self.output
+            .iter()
+            .zip(self.expected_output.iter())
+            .filter(|(output, expected)| output == expected)
+            .count()
+    }
+}
+
+// A port that catalyzes a reaction in the cell when it computes the
+// expected output of problems generated by its task.
+pub struct Catalyst {
+    pub port: Port,
+    pub reaction: Reaction,
+    pub task: Box<dyn Task>,
+    // a closed catalyst poses problems but performs no reactions
+    pub open: bool,
+}
+
+impl Catalyst {
+    pub fn new(reaction: Reaction, task: Box<dyn Task>) -> Catalyst {
+        Catalyst {
+            port: Port::new(),
+            reaction,
+            task,
+            open: true,
+        }
+    }
+
+    // generate a new problem
+    pub fn next(&mut self, rng: &mut dyn RngCore) {
+        let (input, expected_output) = self.task.generate(rng);
+        self.port.reset(&input, &expected_output);
+    }
+
+    // problems are generated on demand
+    fn ensure_problem(&mut self, rng: &mut dyn RngCore) {
+        if self.port.expected_output.is_empty() {
+            self.next(rng);
+        }
+    }
+
+    // a new problem is always available once the old one is done
+    pub fn has_input(&self) -> bool {
+        self.port.expected_output.is_empty() || !self.port.input.is_empty()
+    }
+
+    pub fn read(&mut self, rng: &mut dyn RngCore) -> u32 {
+        self.ensure_problem(rng);
+        self.port.read()
+    }
+
+    // Write a value to the port. Once the output is complete the reaction
+    // is applied once for each correct output value, as far as the pool
+    // allows, and a new problem is generated. A reaction that needs light
+    // only takes place for each correct value with the light (0.0 to 1.0)
+    // as probability. Returns the amount of times the reaction was applied.
+    pub fn write(
+        &mut self,
+        value: u32,
+        pool: &mut Pool,
+        light: f64,
+        rng: &mut dyn RngCore,
+    ) -> usize {
+        self.ensure_problem(rng);
+        self.port.write(value);
+        if !self.port.is_done() {
+            return 0;
+        }
+        let mut correct = if self.open { self.port.correct() } else { 0 };
+        if self.reaction.needs_light {
+            correct = (0..correct).filter(|_| rng.gen_bool(light)).count();
+        }
+        let times = (0..correct)
+            .take_while(|_| pool.apply(&self.reaction))
+            .count();
+        self.next(rng);
+        times
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::reaction::{self, Molecule};
+    use crate::stack;
+    use crate::task::{Fixed, Operator};
+    use enum_map::enum_map;
+    use rand::SeedableRng;
+
+    const SEED: [u8; 16] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
 
     #[test]
     fn test_port() {
@@ -55,4 +162,143 @@ mod tests {
         port.write(40);
         assert!(port.is_done());
     }
+
+    #[test]
+    fn test_port_correct() {
+        let mut port = Port::new();
+        port.reset(&[10, 20], &[30, 40, 50]);
+        port.write(30);
+        port.write(41);
+        port.write(50);
+        assert!(port.is_done());
+        assert_eq!(port.correct(), 2);
+    }
+
+    #[test]
+    fn test_port_restart() {
+        let mut port = Port::new();
+        port.reset(&[10, 20], &[30]);
+        assert_eq!(port.read(), 10);
+        port.write(30);
+        port.restart();
+        assert_eq!(port.output, []);
+        assert_eq!(port.read(), 10);
+        assert_eq!(port.read(), 20);
+    }
+
+    fn reaction() -> Reaction {
+        Reaction::new(enum_map! {
+            Molecule::G => -1,
+            Molecule::A => 10,
+            _ => 0
+        })
+    }
+
+    fn catalyst(input: &[u32], expected_output: &[u32]) -> Catalyst {
+        Catalyst::new(reaction(), Box::new(Fixed::new(input, expected_output)))
+    }
+
+    #[test]
+    fn test_catalyst_correct() {
+        let mut pool = Pool::new();
+        pool.set_amount(Molecule::G, 10);
+        let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+        let mut catalyst = catalyst(&[10, 20], &[30, 40]);
+        assert_eq!(catalyst.read(&mut rng), 10);
+        assert_eq!(catalyst.read(&mut rng), 20);
+        assert_eq!(catalyst.write(30, &mut pool, 1.0, &mut rng), 0);
+        assert_eq!(pool.amount(Molecule::A), 0);
+        assert_eq!(catalyst.write(40, &mut pool, 1.0, &mut rng), 2);
+        assert_eq!(pool.amount(Molecule::A), 20);
+        assert_eq!(pool.amount(Molecule::G), 8);
+        // the problem starts again
+        assert_eq!(catalyst.read(&mut rng), 10);
+    }
+
+    #[test]
+    fn test_catalyst_partially_correct() {
+        let mut pool = Pool::new();
+        pool.set_amount(Molecule::G, 10);
+        let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+        let mut catalyst = catalyst(&[10, 20], &[30, 40]);
+        catalyst.write(30, &mut pool, 1.0, &mut rng);
+        assert_eq!(catalyst.write(41, &mut pool, 1.0, &mut rng), 1);
+        assert_eq!(pool.amount(Molecule::A), 10);
+    }
+
+    #[test]
+    fn test_catalyst_wrong() {
+        let mut pool = Pool::new();
+        pool.set_amount(Molecule::G, 10);
+        let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+        let mut catalyst = catalyst(&[10, 20], &[30]);
+        assert_eq!(catalyst.write(31, &mut pool, 1.0, &mut rng), 0);
+        assert_eq!(pool.amount(Molecule::A), 0);
+    }
+
+    #[test]
+    fn test_catalyst_insufficient_pool() {
+        let mut pool = Pool::new();
+        pool.set_amount(Molecule::G, 1);
+        let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+        let mut catalyst = catalyst(&[10, 20], &[30, 40]);
+        catalyst.write(30, &mut pool, 1.0, &mut rng);
+        assert_eq!(catalyst.write(40, &mut pool, 1.0, &mut rng), 1);
+        assert_eq!(pool.amount(Molecule::A), 10);
+        assert_eq!(pool.amount(Molecule::G), 0);
+    }
+
+    #[test]
+    fn test_catalyst_task() {
+        let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+        let mut pool = Pool::new();
+        pool.set_amount(Molecule::G, 10);
+        let mut catalyst = Catalyst::new(
+            reaction(),
+            Box::new(Operator::new(stack::Instruction::Add, 100).unwrap()),
+        );
+        for i in 1..=3 {
+            assert!(catalyst.has_input());
+            let a = catalyst.read(&mut rng);
+            let b = catalyst.read(&mut rng);
+            assert!(!catalyst.has_input());
+            assert_eq!(catalyst.write(a + b, &mut pool, 1.0, &mut rng), 1);
+            assert_eq!(pool.amount(Molecule::A), i * 10);
+        }
+    }
+
+    #[test]
+    fn test_catalyst_closed() {
+        let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+        let mut catalyst = catalyst(&[10, 20], &[30]);
+        catalyst.open = false;
+        let mut pool = Pool::new();
+        pool.set_amount(Molecule::G, 1);
+        catalyst.read(&mut rng);
+        catalyst.read(&mut rng);
+        assert_eq!(catalyst.write(30, &mut pool, 1.0, &mut rng), 0);
+        assert_eq!(pool.amount(Molecule::G), 1);
+    }
+
+    #[test]
+    fn test_catalyst_light() {
+        let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+        let mut pool = Pool::new();
+        pool.set_amount(Molecule::X, 600);
+        pool.set_amount(Molecule::W, 600);
+        let mut catalyst =
+            Catalyst::new(reaction::photosynthesis(), Box::new(Fixed::new(&[1], &[1])));
+        catalyst.read(&mut rng);
+        assert_eq!(catalyst.write(1, &mut pool, 0.0, &mut rng), 0);
+        catalyst.read(&mut rng);
+        assert_eq!(catalyst.write(1, &mut pool, 1.0, &mut rng), 1);
+        let times: usize = (0..98)
+            .map(|_| {
+                catalyst.read(&mut rng);
+                catalyst.write(1, &mut pool, 0.5, &mut rng)
+            })
+            .sum();
+        assert!(times > 30 && times < 70);
+        assert_eq!(pool.amount(Molecule::G), 1 + times as i64);
+    }
 }
@@ -1,12 +1,20 @@
 use rand::Rng;
+use std::collections::HashMap;
 
 use crate::cell::CellKey;
 use crate::gene::GeneKey;
+use crate::grid::{ring_offsets, Direction, Position, Topology};
+use crate::light::Light;
 use crate::lookup;
+use crate::lookup::Perturbation;
+use crate::organelle::{Gate, Organelle};
+use crate::reaction::{self, Molecule, Reaction};
+use crate::scent::ScentChannel;
 use crate::stack;
-use crate::stack::{nr_to_bool, Stack};
+use crate::stack::{bool_to_nr, nr_to_bool, Stack};
 use crate::triplet::{Mode, Triplet};
 use crate::world::Entities;
+use enum_map::{enum_map, EnumMap};
 use slotmap::new_key_type;
 
 new_key_type! {pub struct ProcessorKey; }
@@ -14,74 +22,277 @@ new_key_type! {pub struct ProcessorKey; }
 pub struct Config {
     pub max_stack_size: usize,
     pub max_call_stack_size: usize,
+    pub max_processors_per_gene: usize,
+    // a processor executes as many instructions per tick as the activation
+    // of its gene, which cannot be raised beyond this
+    pub max_activation: u32,
+    // the most messages that fit in the message queue of a cell
+    pub max_messages: usize,
+    // the most values that fit in the input queue of a connection
+    pub max_connection_input: usize,
+    // the amount of B moved between the pool and the barrier at a time
+    pub barrier_step: i64,
+    // the ATP an attack costs, and the amount of barrier it breaks down
+    pub attack_cost: i64,
+    pub attack_strength: i64,
+    // sensing costs sense_cost ATP times the radius squared, and the radius
+    // cannot go beyond max_sense_radius
+    pub max_sense_radius: u32,
+    pub sense_cost: i64,
+    pub width: usize,
+    pub height: usize,
+    pub topology: Topology,
+    // the amount of B a new cell starts with in its wall
+    pub initial_wall: i64,
+    // the amount of B a wall loses every tick
+    pub wall_decay: i64,
+    pub cell_create_cost: Reaction,
+    // moving a cell turns these molecules into movement
+    pub move_cost: Reaction,
+    // ATP spent to push a number
+    pub number_cost: i64,
+    // ATP spent to execute an instruction not in instruction_costs
+    pub instruction_cost: i64,
+    pub instruction_costs: HashMap<Instruction, i64>,
+    // the stack and call stack entries cost ATP quadratically; every
+    // stack_cost_divisor squared entries costs 1 ATP. 0 means stacks are free
+    pub stack_cost_divisor: i64,
     pub instruction_lookup: lookup::Lookup<Instruction>,
+    // organelle coordinates of the connections, indexed by direction
+    pub connection_coordinates: [u32; 4],
+    pub chem_lookup: lookup::Lookup<Molecule>,
+    // the amount of a molecule moved by Ingest and Expel
+    pub transfer_amount: i64,
+    // the fraction of a molecule in the environment that spreads to
+    // neighboring locations every tick, from 0.0 to 1.0
+    pub diffusion_rates: EnumMap<Molecule, f64>,
+    pub light: Light,
+    pub scent_channels: Vec<ScentChannel>,
+    // every noise_interval-th instruction a processor executes is looked up
+    // again with perturbation, instead of taken from the compiled gene.
+    // 0 means there is no noise
+    pub noise_interval: u32,
+    pub perturbation: Perturbation,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            max_stack_size: 1000,
+            max_call_stack_size: 1000,
+            max_processors_per_gene: 10,
+            max_activation: 10,
+            max_messages: 100,
+            max_connection_input: 100,
+            barrier_step: 10,
+            attack_cost: 10,
+            attack_strength: 10,
+            max_sense_radius: 10,
+            sense_cost: 1,
+            width: 100,
+            height: 100,
+            topology: Topology::Toroidal,
+            initial_wall: 100,
+            wall_decay: 0,
+            cell_create_cost: Reaction::new(enum_map! { _ => 0 }),
+            move_cost: Reaction::new(enum_map! { _ => 0 }),
+            number_cost: 0,
+            instruction_cost: 0,
+            instruction_costs: HashMap::new(),
+            stack_cost_divisor: 0,
+            instruction_lookup: instruction_lookup(),
+            connection_coordinates: [0x100000, 0x200000, 0x300000, 0x400000],
+            chem_lookup: reaction::chem_lookup(),
+            transfer_amount: 1,
+            diffusion_rates: enum_map! { _ => 0.0 },
+            light: Light::Constant(1.0),
+            scent_channels: vec![
+                ScentChannel {
+                    diffusion: 0.2,
+                    decay: 0.05,
+                };
+                4
+            ],
+            noise_interval: 0,
+            perturbation: Perturbation::default(),
+        }
+    }
+}
+
+pub fn instruction_lookup() -> lookup::Lookup<Instruction> {
+    let mut l = lookup::Lookup::<Instruction>::new();
+    let mut add = |instruction: Instruction| {
+        l.add(instruction.coordinates(), instruction)
+            .expect("Cannot add!")
+    };
+    for &instruction in stack::Instruction::all().iter() {
+        add(Instruction::StackInstruction(instruction));
+    }
+    for &instruction in ProcessorInstruction::all().iter() {
+        add(Instruction::ProcessorInstruction(instruction));
+    }
+    l
 }
 
 pub struct Processor {
     cell_key: CellKey,
+    // the gene the processor was started on
+    start_gene_key: GeneKey,
     gene_key: GeneKey,
     pub stack: Vec<u32>,
     pub call_stack: Vec<(u32, usize)>,
     pc: usize,
     pub failures: u32,
+    // instructions executed since the last perturbed one
+    since_noise: u32,
 }
 
 impl Processor {
     pub fn new(cell_key: CellKey, gene_key: GeneKey) -> Processor {
-        return Processor {
-            cell_key: cell_key,
-            gene_key: gene_key,
+        Processor::new_at(cell_key, gene_key, 0)
+    }
+
+    pub fn new_at(cell_key: CellKey, gene_key: GeneKey, pc: usize) -> Processor {
+        Processor {
+            cell_key,
+            start_gene_key: gene_key,
+            gene_key,
             stack: vec![],
             call_stack: vec![],
-            pc: 0,
+            pc,
             failures: 0,
+            since_noise: 0,
+        }
+    }
+
+    pub fn cell_key(&self) -> CellKey {
+        self.cell_key
+    }
+
+    pub fn gene_key(&self) -> GeneKey {
+        self.gene_key
+    }
+
+    pub fn start_gene_key(&self) -> GeneKey {
+        self.start_gene_key
+    }
+
+    pub fn move_to_cell(&mut self, cell_key: CellKey) {
+        // we cannot return to genes in the old cell
+        self.cell_key = cell_key;
+        self.call_stack.clear();
+    }
+
+    // the instruction to execute next, perturbed if it is time for noise
+    pub fn next_instruction<R: Rng>(
+        &self,
+        entities: &Entities,
+        config: &Config,
+        rng: &mut R,
+    ) -> CompiledInstruction {
+        let gene = &entities.genes[self.gene_key];
+        if self.is_noisy(config) {
+            CompiledInstruction::compile_perturbed(gene.code()[self.pc], config, rng)
+        } else {
+            gene.compiled(self.pc)
+        }
+    }
+
+    // the amount of ATP it takes to execute an instruction
+    pub fn cost(&self, compiled: CompiledInstruction, config: &Config) -> i64 {
+        let instruction_cost = match compiled {
+            CompiledInstruction::Number(_) => config.number_cost,
+            CompiledInstruction::Instruction(Some(instruction)) => {
+                config
+                    .instruction_costs
+                    .get(&instruction)
+                    .copied()
+                    .unwrap_or(config.instruction_cost)
+                    + self.sense_cost(&instruction, config)
+            }
+            CompiledInstruction::Instruction(None) => config.instruction_cost,
+            CompiledInstruction::Call(_) => config.instruction_cost,
+            CompiledInstruction::Noop => config.instruction_cost,
         };
+        instruction_cost + self.stack_cost(config)
+    }
+
+    // sensing further away costs more; the radius is on top of the stack
+    fn sense_cost(&self, instruction: &Instruction, config: &Config) -> i64 {
+        match instruction {
+            Instruction::ProcessorInstruction(ProcessorInstruction::SenseCell)
+            | Instruction::ProcessorInstruction(ProcessorInstruction::SenseChem) => {
+                let radius = self
+                    .stack
+                    .last()
+                    .map_or(0, |&radius| sense_radius(radius, config));
+                config.sense_cost * (radius * radius) as i64
+            }
+            _ => 0,
+        }
+    }
+
+    fn stack_cost(&self, config: &Config) -> i64 {
+        if config.stack_cost_divisor == 0 {
+            return 0;
+        }
+        let stack_len = self.stack.len() as i64;
+        let call_stack_len = self.call_stack.len() as i64;
+        (stack_len * stack_len + call_stack_len * call_stack_len) / config.stack_cost_divisor
     }
 
+    // execute an instruction, as given by next_instruction
     pub fn execute<R: Rng>(
         &mut self,
+        compiled: CompiledInstruction,
         entities: &Entities,
         config: &Config,
         rng: &mut R,
     ) -> Option<Action> {
-        let value = entities.genes[self.gene_key].code[self.pc];
+        if config.noise_interval > 0 {
+            self.since_noise = (self.since_noise + 1) % config.noise_interval;
+        }
 
         // now increase pc
         self.pc += 1;
 
-        let t = Triplet::from_int(value);
-        let action: Option<Action> = match t.mode {
-            Mode::Number => {
-                // println!("number: {}", value);
+        let action: Option<Action> = match compiled {
+            CompiledInstruction::Number(value) => {
                 self.stack.push(value);
                 None
             }
-            Mode::Instruction => {
-                let instruction = config.instruction_lookup.find(value);
-                // println!("value {:x?}, instruction: {:?}", value, instruction);
-                let action = instruction.execute(self, entities, config, rng);
+            CompiledInstruction::Instruction(instruction) => {
+                let action = instruction
+                    .and_then(|instruction| instruction.execute(self, entities, config, rng));
                 if action.is_none() {
                     self.failures += 1;
                 }
                 action
             }
-            Mode::Call => None,
-            Mode::Noop => None,
+            CompiledInstruction::Call(coordinates) => {
+                // a call baked into the gene, by the coordinates of the gene
+                let action = entities.cells[self.cell_key]
+                    .lookup_gene_key(coordinates)
+                    .map(|gene_key| self.call_gene(gene_key, entities, config));
+                if action.is_none() {
+                    self.failures += 1;
+                }
+                action
+            }
+            CompiledInstruction::Noop => None,
         };
 
         // at the end
-        if self.pc >= entities.genes[self.gene_key].code.len() {
-            let top = self.call_stack.pop();
-            match top {
-                Some((gene_id, return_pc)) => {
-                    // return to calling gene
-                    // XXX must check for gene_id being valid! unwrap isn't safe
-                    self.gene_key = entities.get_gene_key(self.cell_key, gene_id).unwrap();
+        if self.pc >= entities.genes[self.gene_key].code().len() {
+            // go back to start, unless we can return to a calling gene
+            self.pc = 0;
+            // a calling gene may have been moved out of the cell in the
+            // mean time, in which case we skip it
+            while let Some((gene_id, return_pc)) = self.call_stack.pop() {
+                if let Some(gene_key) = entities.get_gene_key(self.cell_key, gene_id) {
+                    self.gene_key = gene_key;
                     self.pc = return_pc;
-                }
-                None => {
-                    // go back to start
-                    self.pc = 0;
+                    break;
                 }
             }
         }
@@ -89,6 +300,11 @@ impl Processor {
         action
     }
 
+    // whether the next instruction is the next perturbation point
+    fn is_noisy(&self, config: &Config) -> bool {
+        config.noise_interval > 0 && self.since_noise + 1 == config.noise_interval
+    }
+
     pub fn shrink_stack_on_overflow(&mut self, config: &Config) {
         if self.stack.len() <= config.max_stack_size {
             return;
@@ -110,7 +326,7 @@ impl Processor {
     fn jump(&mut self, adjust: i32, entities: &Entities) -> Option<Action> {
         let new_pc: i32 = (self.pc as i32) + adjust;
         let gene = &entities.genes[self.gene_key];
-        if new_pc < 0 || new_pc >= (gene.code.len() as i32) {
+        if new_pc < 0 || new_pc >= (gene.code().len() as i32) {
             return None;
         }
         self.pc = new_pc as usize;
@@ -118,23 +334,30 @@ impl Processor {
     }
 
     fn call(&mut self, gene_id: u32, entities: &Entities, config: &Config) -> Option<Action> {
-        let gene = &entities.genes[self.gene_key];
         entities
             .get_gene_key(self.cell_key, gene_id)
-            .and_then(|call_gene_key| {
-                let return_pc = {
-                    if self.pc >= gene.code.len() {
-                        0
-                    } else {
-                        self.pc
-                    }
-                };
-                self.call_stack.push((gene.id, return_pc));
-                self.shrink_call_stack_on_overflow(config);
-                self.gene_key = call_gene_key;
-                self.pc = 0;
-                Some(Action::Noop)
-            })
+            .map(|call_gene_key| self.call_gene(call_gene_key, entities, config))
+    }
+
+    fn call_gene(
+        &mut self,
+        call_gene_key: GeneKey,
+        entities: &Entities,
+        config: &Config,
+    ) -> Action {
+        let gene = &entities.genes[self.gene_key];
+        let return_pc = {
+            if self.pc >= gene.code().len() {
+                0
+            } else {
+                self.pc
+            }
+        };
+        self.call_stack.push((gene.id, return_pc));
+        self.shrink_call_stack_on_overflow(config);
+        self.gene_key = call_gene_key;
+        self.pc = 0;
+        Action::Noop
     }
 
     fn gene_read(&mut self, gene_id: u32, index: u32, entities: &Entities) -> Option<Action> {
@@ -142,42 +365,368 @@ impl Processor {
             .get_gene_key(self.cell_key, gene_id)
             .and_then(|gene_key| {
                 let gene = &entities.genes[gene_key];
-                if index >= gene.code.len() as u32 {
+                if index >= gene.code().len() as u32 {
                     return None;
                 }
-                self.stack.push(gene.code[index as usize]);
+                self.stack.push(gene.code()[index as usize]);
                 Some(Action::Noop)
             })
     }
 
     fn gene_write(&self, gene_id: u32, value: u32, entities: &Entities) -> Option<Action> {
+        if !self.can_build(1, entities) {
+            return None;
+        }
         entities
             .get_gene_key(self.cell_key, gene_id)
-            .and_then(|gene_key| Some(Action::GeneWrite(gene_key, value)))
-    }
-
-    // fn proc_start(&self, gene_id: u32, index: u32, entities: &Entities) -> Option<()> {
-    //     entities
-    //         .get_gene_key(self.cell_key, gene_id)
-    //         .and_then(|gene_key| {
-    //             let gene = &entities.genes[gene_key];
-    //             if index >= gene.code.len() as u32 {
-    //                 return None;
-    //             }
-    //             // XXX not finished yet
-    //             // world.add_processor(gene_key, index);
-    //             Some(())
-    //         })
-    // }
+            .map(|gene_key| Action::GeneWrite(gene_key, value))
+    }
+
+    // genes are built from one I per instruction, taken from the cell
+    fn can_build(&self, instructions: usize, entities: &Entities) -> bool {
+        entities.cells[self.cell_key].pool.amount(Molecule::I) >= instructions as i64
+    }
+
+    fn proc_create(&self, gene_id: u32, index: u32, entities: &Entities) -> Option<Action> {
+        entities
+            .get_gene_key(self.cell_key, gene_id)
+            .and_then(|gene_key| {
+                let gene = &entities.genes[gene_key];
+                if index >= gene.code().len() as u32 {
+                    return None;
+                }
+                Some(Action::ProcCreate(self.cell_key, gene_key, index as usize))
+            })
+    }
+
+    fn cell_create(
+        &mut self,
+        direction: Direction,
+        entities: &Entities,
+        config: &Config,
+    ) -> Option<Action> {
+        let grid = &entities.grid;
+        let cell_key = self.cell_key;
+        let position = grid
+            .position(cell_key)
+            .and_then(|position| grid.neighbor_position(position, direction))
+            .filter(|&position| grid.is_free(position))
+            .filter(|_| {
+                // the parent also gives the new cell its wall, and must
+                // keep some wall itself
+                let pool = &entities.cells[cell_key].pool;
+                let cost = &config.cell_create_cost;
+                pool.can_apply(cost)
+                    && pool.amount(Molecule::B) + cost.change(Molecule::B) > config.initial_wall
+            });
+        self.stack.push(bool_to_nr(position.is_some()));
+        Some(position.map_or(Action::Noop, |position| {
+            Action::CellCreate(cell_key, direction, position)
+        }))
+    }
+
+    fn cell_move(
+        &mut self,
+        direction: Direction,
+        entities: &Entities,
+        config: &Config,
+    ) -> Option<Action> {
+        let grid = &entities.grid;
+        let cell_key = self.cell_key;
+        let position = grid
+            .position(cell_key)
+            .and_then(|position| grid.neighbor_position(position, direction))
+            .filter(|&position| grid.is_free(position))
+            .filter(|_| entities.cells[cell_key].pool.can_apply(&config.move_cost));
+        self.stack.push(bool_to_nr(position.is_some()));
+        Some(position.map_or(Action::Noop, |position| {
+            Action::CellMove(cell_key, position)
+        }))
+    }
+
+    fn gene_transfer(
+        &self,
+        gene_id: u32,
+        direction: Direction,
+        entities: &Entities,
+    ) -> Option<(GeneKey, CellKey)> {
+        let gene_key = entities.get_gene_key(self.cell_key, gene_id)?;
+        let target_cell_key = entities.connection_target(self.cell_key, direction, Gate::Gene)?;
+        Some((gene_key, target_cell_key))
+    }
+
+    fn gene_move(&self, gene_id: u32, direction: Direction, entities: &Entities) -> Option<Action> {
+        self.gene_transfer(gene_id, direction, entities)
+            .map(|(gene_key, target_cell_key)| {
+                Action::GeneMove(gene_key, self.cell_key, target_cell_key)
+            })
+    }
+
+    fn gene_copy<R: Rng>(
+        &self,
+        gene_id: u32,
+        direction: Direction,
+        entities: &Entities,
+        rng: &mut R,
+    ) -> Option<Action> {
+        self.gene_transfer(gene_id, direction, entities)
+            .filter(|&(gene_key, _)| {
+                self.can_build(entities.genes[gene_key].code().len(), entities)
+            })
+            .map(|(gene_key, target_cell_key)| {
+                let id = entities.create_gene_id(rng);
+                Action::GeneCopy(gene_key, target_cell_key, id)
+            })
+    }
+
+    // the organelle with org_id, if the cell has it
+    fn organelle(&self, org_id: u32, entities: &Entities) -> Option<usize> {
+        let org_id = org_id as usize;
+        if org_id >= entities.cells[self.cell_key].organelles.len() {
+            return None;
+        }
+        Some(org_id)
+    }
+
+    fn output(
+        &self,
+        org_id: u32,
+        value: u32,
+        entities: &Entities,
+        config: &Config,
+    ) -> Option<Action> {
+        let org_id = self.organelle(org_id, entities)?;
+        if let Organelle::Connection(connection) = &entities.cells[self.cell_key].organelles[org_id]
+        {
+            // the value cannot be delivered if the input on the other side
+            // is full
+            if let Some((peer_key, peer_org_id)) =
+                entities.connection_peer(self.cell_key, connection.direction)
+            {
+                if let Organelle::Connection(peer_connection) =
+                    &entities.cells[peer_key].organelles[peer_org_id]
+                {
+                    if peer_connection.input.len() >= config.max_connection_input {
+                        return None;
+                    }
+                }
+            }
+        }
+        Some(Action::Output(self.cell_key, org_id, value))
+    }
+
+    fn set_open(&self, org_id: u32, gate: Gate, open: bool, entities: &Entities) -> Option<Action> {
+        let org_id = self.organelle(org_id, entities)?;
+        // the organelle must have the gate
+        entities.cells[self.cell_key].organelles[org_id].is_open(gate)?;
+        Some(Action::SetOpen(self.cell_key, org_id, gate, open))
+    }
+
+    fn is_open(&mut self, org_id: u32, gate: Gate, entities: &Entities) -> Option<Action> {
+        let org_id = self.organelle(org_id, entities)?;
+        let open = entities.cells[self.cell_key].organelles[org_id].is_open(gate)?;
+        self.stack.push(bool_to_nr(open));
+        Some(Action::Noop)
+    }
+
+    fn ingest(
+        &self,
+        org_id: u32,
+        chem: u32,
+        entities: &Entities,
+        config: &Config,
+    ) -> Option<Action> {
+        let org_id = self.organelle(org_id, entities)?;
+        let molecule = *config.chem_lookup.find(chem)?;
+        let cell = &entities.cells[self.cell_key];
+        match &cell.organelles[org_id] {
+            Organelle::Environment(environment) if environment.chem_open => {
+                let position = entities.grid.position(self.cell_key)?;
+                let amount = config.transfer_amount;
+                if !entities
+                    .grid
+                    .environment(position)
+                    .can_transfer(&cell.pool, molecule, amount)
+                {
+                    return None;
+                }
+                Some(Action::Ingest(self.cell_key, molecule, amount))
+            }
+            _ => None,
+        }
+    }
+
+    fn expel(
+        &self,
+        org_id: u32,
+        chem: u32,
+        entities: &Entities,
+        config: &Config,
+    ) -> Option<Action> {
+        let org_id = self.organelle(org_id, entities)?;
+        let molecule = *config.chem_lookup.find(chem)?;
+        let cell = &entities.cells[self.cell_key];
+        let amount = config.transfer_amount;
+        match &cell.organelles[org_id] {
+            Organelle::Environment(environment) if environment.chem_open => {
+                let position = entities.grid.position(self.cell_key)?;
+                if !cell
+                    .pool
+                    .can_transfer(entities.grid.environment(position), molecule, amount)
+                {
+                    return None;
+                }
+                Some(Action::Expel(self.cell_key, molecule, amount))
+            }
+            Organelle::Connection(connection) => {
+                let target_cell_key =
+                    entities.connection_target(self.cell_key, connection.direction, Gate::Chem)?;
+                if !cell
+                    .pool
+                    .can_transfer(&entities.cells[target_cell_key].pool, molecule, amount)
+                {
+                    return None;
+                }
+                Some(Action::MoleculeMove(
+                    self.cell_key,
+                    target_cell_key,
+                    molecule,
+                    amount,
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    // channel numbers wrap around
+    fn scent_channel(&self, channel: u32, entities: &Entities) -> Option<usize> {
+        let channels = entities.scents.channels();
+        if channels == 0 {
+            return None;
+        }
+        Some(channel as usize % channels)
+    }
+
+    fn smell(&mut self, channel: u32, direction: Direction, entities: &Entities) -> Option<Action> {
+        let channel = self.scent_channel(channel, entities)?;
+        let grid = &entities.grid;
+        let position = grid
+            .position(self.cell_key)
+            .and_then(|position| grid.neighbor_position(position, direction))?;
+        let amount = entities.scents.amount(channel, position);
+        self.stack.push(amount as u32);
+        Some(Action::Noop)
+    }
+
+    fn send(
+        &self,
+        value: u32,
+        direction: Direction,
+        entities: &Entities,
+        config: &Config,
+    ) -> Option<Action> {
+        // messages are not affected by barriers or connections
+        let target_cell_key = entities.grid.neighbor(self.cell_key, direction)?;
+        if entities.cells[target_cell_key].messages.len() >= config.max_messages {
+            return None;
+        }
+        Some(Action::Send(target_cell_key, value))
+    }
+
+    fn attack(&self, direction: Direction, entities: &Entities, config: &Config) -> Option<Action> {
+        let target_cell_key = entities.grid.neighbor(self.cell_key, direction)?;
+        if entities.cells[self.cell_key].pool.amount(Molecule::A) < config.attack_cost {
+            return None;
+        }
+        Some(Action::Attack(self.cell_key, target_cell_key))
+    }
+
+    // the offset of the nearest other cell within radius
+    fn sense_cell(&mut self, radius: usize, entities: &Entities) -> Option<Action> {
+        let grid = &entities.grid;
+        let position = grid.position(self.cell_key)?;
+        let (dx, dy) = (1..=radius).flat_map(ring_offsets).find(|&(dx, dy)| {
+            grid.offset_position(position, dx, dy)
+                .and_then(|position| grid.get(position))
+                .is_some_and(|cell_key| cell_key != self.cell_key)
+        })?;
+        self.stack.push(dx as i32 as u32);
+        self.stack.push(dy as i32 as u32);
+        Some(Action::Noop)
+    }
+
+    // the offset of the location within radius with the most of chem in its
+    // environment, the nearest one if there are more
+    fn sense_chem(
+        &mut self,
+        chem: u32,
+        radius: usize,
+        entities: &Entities,
+        config: &Config,
+    ) -> Option<Action> {
+        let molecule = *config.chem_lookup.find(chem)?;
+        let grid = &entities.grid;
+        let position = grid.position(self.cell_key)?;
+        let mut best = (0, 0, -1);
+        for (dx, dy) in (0..=radius).flat_map(ring_offsets) {
+            if let Some(position) = grid.offset_position(position, dx, dy) {
+                let amount = grid.environment(position).amount(molecule);
+                if amount > best.2 {
+                    best = (dx, dy, amount);
+                }
+            }
+        }
+        let (dx, dy, amount) = best;
+        self.stack.push(dx as i32 as u32);
+        self.stack.push(dy as i32 as u32);
+        self.stack.push(amount_to_nr(amount));
+        Some(Action::Noop)
+    }
+
+    fn proc_end(&self, gene_id: u32, entities: &Entities) -> Option<Action> {
+        entities
+            .get_gene_key(self.cell_key, gene_id)
+            .map(Action::ProcEnd)
+    }
+}
+
+fn sense_radius(radius: u32, config: &Config) -> usize {
+    radius.min(config.max_sense_radius) as usize
+}
+
+// amounts that do not fit are reported as the maximum
+fn amount_to_nr(amount: i64) -> u32 {
+    amount.clamp(0, u32::MAX as i64) as u32
 }
 
 pub enum Action {
     Noop,
     GeneWrite(GeneKey, u32),
     GeneCreate(CellKey, u32),
+    ProcCreate(CellKey, GeneKey, usize),
+    ProcDestroy,
+    ProcEnd(GeneKey),
+    CellCreate(CellKey, Direction, Position),
+    CellMove(CellKey, Position),
+    GeneMove(GeneKey, CellKey, CellKey),
+    GeneCopy(GeneKey, CellKey, u32),
+    Input(CellKey, usize),
+    Output(CellKey, usize, u32),
+    Ingest(CellKey, Molecule, i64),
+    Expel(CellKey, Molecule, i64),
+    MoleculeMove(CellKey, CellKey, Molecule, i64),
+    Emit(CellKey, usize, u32),
+    Excite(GeneKey),
+    Inhibit(GeneKey),
+    Send(CellKey, u32),
+    Receive(CellKey),
+    RaiseBarrier(CellKey, i64),
+    LowerBarrier(CellKey, i64),
+    Attack(CellKey, CellKey),
+    SetOpen(CellKey, usize, Gate, bool),
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum ProcessorInstruction {
     JF = 0x010100,
     JB = 0x010110,
@@ -186,7 +735,41 @@ pub enum ProcessorInstruction {
     GeneRead = 0x010140,
     GeneWrite = 0x010150,
     GeneCreate = 0x010160,
-    // ProcStart = 0x010160,
+    ProcCreate = 0x010170,
+    ProcDestroy = 0x010180,
+    ProcEnd = 0x010190,
+    Cell = 0x010200,
+    MoveGene = 0x010210,
+    CopyGene = 0x010220,
+    Move = 0x010230,
+    LookupOrg = 0x010300,
+    Input = 0x010310,
+    HasInput = 0x010320,
+    Output = 0x010330,
+    Ingest = 0x010400,
+    Expel = 0x010410,
+    Emit = 0x010500,
+    Smell = 0x010510,
+    Observe = 0x010520,
+    Excite = 0x010600,
+    Inhibit = 0x010610,
+    Activation = 0x010620,
+    Send = 0x010700,
+    Receive = 0x010710,
+    RaiseBarrier = 0x010800,
+    LowerBarrier = 0x010810,
+    Attack = 0x010820,
+    ChemAmount = 0x010900,
+    WallAmount = 0x010910,
+    EnvAmount = 0x010920,
+    SenseCell = 0x010a00,
+    SenseChem = 0x010a10,
+    OpenGene = 0x010340,
+    OpenChem = 0x010350,
+    CloseGene = 0x010360,
+    CloseChem = 0x010370,
+    IsOpenGene = 0x010380,
+    IsOpenChem = 0x010390,
 }
 
 impl<'a> ProcessorInstruction {
@@ -217,10 +800,12 @@ impl<'a> ProcessorInstruction {
                 processor.jump(-(second as i32 + 1), entities)
             }),
             ProcessorInstruction::Lookup => processor.stack.pop().and_then(|first| {
-                processor.stack.push(
-                    entities.cells[processor.cell_key].lookup_gene_id(&entities.genes, first),
-                );
-                Some(Action::Noop)
+                entities.cells[processor.cell_key]
+                    .lookup_gene_id(&entities.genes, first)
+                    .map(|gene_id| {
+                        processor.stack.push(gene_id);
+                        Action::Noop
+                    })
             }),
             ProcessorInstruction::Call => processor
                 .stack
@@ -235,28 +820,292 @@ impl<'a> ProcessorInstruction {
                 .pop2()
                 .and_then(|(first, second)| processor.gene_write(first, second, entities)),
             ProcessorInstruction::GeneCreate => {
+                if !processor.can_build(1, entities) {
+                    return None;
+                }
                 let id = entities.create_gene_id(rng);
                 processor.stack.push(id);
                 Some(Action::GeneCreate(processor.cell_key, id))
             }
-                // ProcessorInstruction::ProcStart => processor
-            //     .stack
-            //     .pop2()
-            //     .and_then(|(first, second)| processor.proc_start(first, second, entities)),
+            ProcessorInstruction::ProcCreate => processor
+                .stack
+                .pop2()
+                .and_then(|(first, second)| processor.proc_create(first, second, entities)),
+            ProcessorInstruction::ProcDestroy => Some(Action::ProcDestroy),
+            ProcessorInstruction::ProcEnd => processor
+                .stack
+                .pop()
+                .and_then(|first| processor.proc_end(first, entities)),
+            ProcessorInstruction::Cell => processor.stack.pop().and_then(|first| {
+                processor.cell_create(Direction::from_nr(first), entities, config)
+            }),
+            ProcessorInstruction::MoveGene => processor.stack.pop2().and_then(|(first, second)| {
+                processor.gene_move(first, Direction::from_nr(second), entities)
+            }),
+            ProcessorInstruction::CopyGene => processor.stack.pop2().and_then(|(first, second)| {
+                processor.gene_copy(first, Direction::from_nr(second), entities, rng)
+            }),
+            ProcessorInstruction::Move => processor
+                .stack
+                .pop()
+                .and_then(|first| processor.cell_move(Direction::from_nr(first), entities, config)),
+            ProcessorInstruction::LookupOrg => processor.stack.pop().and_then(|first| {
+                entities.cells[processor.cell_key]
+                    .lookup_organelle_id(first)
+                    .map(|org_id| {
+                        processor.stack.push(org_id as u32);
+                        Action::Noop
+                    })
+            }),
+            ProcessorInstruction::Input => processor
+                .stack
+                .pop()
+                .and_then(|first| processor.organelle(first, entities))
+                .map(|org_id| Action::Input(processor.cell_key, org_id)),
+            ProcessorInstruction::HasInput => processor.stack.pop().and_then(|first| {
+                processor.organelle(first, entities).map(|org_id| {
+                    let has_input =
+                        entities.cells[processor.cell_key].organelles[org_id].has_input();
+                    processor.stack.push(bool_to_nr(has_input));
+                    Action::Noop
+                })
+            }),
+            ProcessorInstruction::Output => processor
+                .stack
+                .pop2()
+                .and_then(|(first, second)| processor.output(first, second, entities, config)),
+            ProcessorInstruction::OpenGene => processor
+                .stack
+                .pop()
+                .and_then(|first| processor.set_open(first, Gate::Gene, true, entities)),
+            ProcessorInstruction::OpenChem => processor
+                .stack
+                .pop()
+                .and_then(|first| processor.set_open(first, Gate::Chem, true, entities)),
+            ProcessorInstruction::CloseGene => processor
+                .stack
+                .pop()
+                .and_then(|first| processor.set_open(first, Gate::Gene, false, entities)),
+            ProcessorInstruction::CloseChem => processor
+                .stack
+                .pop()
+                .and_then(|first| processor.set_open(first, Gate::Chem, false, entities)),
+            ProcessorInstruction::IsOpenGene => processor
+                .stack
+                .pop()
+                .and_then(|first| processor.is_open(first, Gate::Gene, entities)),
+            ProcessorInstruction::IsOpenChem => processor
+                .stack
+                .pop()
+                .and_then(|first| processor.is_open(first, Gate::Chem, entities)),
+            ProcessorInstruction::Ingest => processor
+                .stack
+                .pop2()
+                .and_then(|(first, second)| processor.ingest(first, second, entities, config)),
+            ProcessorInstruction::Expel => processor
+                .stack
+                .pop2()
+                .and_then(|(first, second)| processor.expel(first, second, entities, config)),
+            ProcessorInstruction::Emit => processor.stack.pop2().and_then(|(first, second)| {
+                processor
+                    .scent_channel(first, entities)
+                    .map(|channel| Action::Emit(processor.cell_key, channel, second))
+            }),
+            ProcessorInstruction::Smell => processor.stack.pop2().and_then(|(first, second)| {
+                processor.smell(first, Direction::from_nr(second), entities)
+            }),
+            ProcessorInstruction::Observe => processor.stack.pop().map(|first| {
+                let signature = entities.observe(processor.cell_key, Direction::from_nr(first));
+                processor.stack.extend_from_slice(&signature);
+                Action::Noop
+            }),
+            ProcessorInstruction::Excite => processor.stack.pop().and_then(|first| {
+                entities
+                    .get_gene_key(processor.cell_key, first)
+                    .map(Action::Excite)
+            }),
+            ProcessorInstruction::Inhibit => processor.stack.pop().and_then(|first| {
+                entities
+                    .get_gene_key(processor.cell_key, first)
+                    .map(Action::Inhibit)
+            }),
+            ProcessorInstruction::Activation => processor.stack.pop().and_then(|first| {
+                entities
+                    .get_gene_key(processor.cell_key, first)
+                    .map(|gene_key| {
+                        processor.stack.push(entities.genes[gene_key].activation);
+                        Action::Noop
+                    })
+            }),
+            ProcessorInstruction::Send => processor.stack.pop2().and_then(|(first, second)| {
+                processor.send(first, Direction::from_nr(second), entities, config)
+            }),
+            ProcessorInstruction::Receive => {
+                if entities.cells[processor.cell_key].messages.is_empty() {
+                    return None;
+                }
+                Some(Action::Receive(processor.cell_key))
+            }
+            ProcessorInstruction::RaiseBarrier => {
+                let amount = config.barrier_step;
+                // the wall has to survive the next decay
+                let wall = entities.cells[processor.cell_key].pool.amount(Molecule::B);
+                if wall - amount <= config.wall_decay {
+                    return None;
+                }
+                Some(Action::RaiseBarrier(processor.cell_key, amount))
+            }
+            ProcessorInstruction::LowerBarrier => {
+                // what does not fit in the pool stays in the barrier
+                let cell = &entities.cells[processor.cell_key];
+                let room = cell.pool.maximum(Molecule::B) - cell.pool.amount(Molecule::B);
+                let amount = config.barrier_step.min(cell.barrier()).min(room);
+                if amount <= 0 {
+                    return None;
+                }
+                Some(Action::LowerBarrier(processor.cell_key, amount))
+            }
+            ProcessorInstruction::Attack => processor
+                .stack
+                .pop()
+                .and_then(|first| processor.attack(Direction::from_nr(first), entities, config)),
+            ProcessorInstruction::ChemAmount => processor.stack.pop().and_then(|first| {
+                config.chem_lookup.find(first).map(|&molecule| {
+                    let amount = entities.cells[processor.cell_key].pool.amount(molecule);
+                    processor.stack.push(amount_to_nr(amount));
+                    Action::Noop
+                })
+            }),
+            ProcessorInstruction::WallAmount => {
+                let amount = entities.cells[processor.cell_key].pool.amount(Molecule::B);
+                processor.stack.push(amount_to_nr(amount));
+                Some(Action::Noop)
+            }
+            ProcessorInstruction::EnvAmount => processor.stack.pop().and_then(|first| {
+                let molecule = *config.chem_lookup.find(first)?;
+                let position = entities.grid.position(processor.cell_key)?;
+                let amount = entities.grid.environment(position).amount(molecule);
+                processor.stack.push(amount_to_nr(amount));
+                Some(Action::Noop)
+            }),
+            ProcessorInstruction::SenseCell => processor
+                .stack
+                .pop()
+                .and_then(|first| processor.sense_cell(sense_radius(first, config), entities)),
+            ProcessorInstruction::SenseChem => {
+                processor.stack.pop2().and_then(|(first, second)| {
+                    processor.sense_chem(first, sense_radius(second, config), entities, config)
+                })
+            }
         }
     }
 
     pub fn coordinates(&self) -> u32 {
         *self as u32
     }
+
+    pub fn all() -> [ProcessorInstruction; 42] {
+        [
+            ProcessorInstruction::JF,
+            ProcessorInstruction::JB,
+            ProcessorInstruction::Lookup,
+            ProcessorInstruction::Call,
+            ProcessorInstruction::GeneRead,
+            ProcessorInstruction::GeneWrite,
+            ProcessorInstruction::GeneCreate,
+            ProcessorInstruction::ProcCreate,
+            ProcessorInstruction::ProcDestroy,
+            ProcessorInstruction::ProcEnd,
+            ProcessorInstruction::Cell,
+            ProcessorInstruction::MoveGene,
+            ProcessorInstruction::CopyGene,
+            ProcessorInstruction::Move,
+            ProcessorInstruction::LookupOrg,
+            ProcessorInstruction::Input,
+            ProcessorInstruction::HasInput,
+            ProcessorInstruction::Output,
+            ProcessorInstruction::OpenGene,
+            ProcessorInstruction::OpenChem,
+            ProcessorInstruction::CloseGene,
+            ProcessorInstruction::CloseChem,
+            ProcessorInstruction::IsOpenGene,
+            ProcessorInstruction::IsOpenChem,
+            ProcessorInstruction::Ingest,
+            ProcessorInstruction::Expel,
+            ProcessorInstruction::Emit,
+            ProcessorInstruction::Smell,
+            ProcessorInstruction::Observe,
+            ProcessorInstruction::Excite,
+            ProcessorInstruction::Inhibit,
+            ProcessorInstruction::Activation,
+            ProcessorInstruction::Send,
+            ProcessorInstruction::Receive,
+            ProcessorInstruction::RaiseBarrier,
+            ProcessorInstruction::LowerBarrier,
+            ProcessorInstruction::Attack,
+            ProcessorInstruction::ChemAmount,
+            ProcessorInstruction::WallAmount,
+            ProcessorInstruction::EnvAmount,
+            ProcessorInstruction::SenseCell,
+            ProcessorInstruction::SenseChem,
+        ]
+    }
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum Instruction {
     StackInstruction(stack::Instruction),
     ProcessorInstruction(ProcessorInstruction),
 }
 
+// A value in a gene with its instruction already looked up, so that
+// executing it does not need a lookup anymore.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CompiledInstruction {
+    Number(u32),
+    // None if there is no instruction to look up at all
+    Instruction(Option<Instruction>),
+    // the coordinates of the gene to call
+    Call(u32),
+    Noop,
+}
+
+impl CompiledInstruction {
+    pub fn compile(
+        value: u32,
+        instruction_lookup: &lookup::Lookup<Instruction>,
+    ) -> CompiledInstruction {
+        match Triplet::from_int(value).mode {
+            Mode::Number => CompiledInstruction::Number(value),
+            Mode::Instruction => {
+                CompiledInstruction::Instruction(instruction_lookup.find(value).copied())
+            }
+            Mode::Call => CompiledInstruction::Call(value & 0xFFFFFF),
+            Mode::Noop => CompiledInstruction::Noop,
+        }
+    }
+
+    // compile with noise on the lookup of instructions and called genes
+    pub fn compile_perturbed<R: Rng>(
+        value: u32,
+        config: &Config,
+        rng: &mut R,
+    ) -> CompiledInstruction {
+        let perturbation = &config.perturbation;
+        match Triplet::from_int(value).mode {
+            Mode::Number => CompiledInstruction::Number(value),
+            Mode::Instruction => CompiledInstruction::Instruction(
+                config
+                    .instruction_lookup
+                    .find_perturbed(value, perturbation, rng)
+                    .copied(),
+            ),
+            Mode::Call => CompiledInstruction::Call(perturbation.perturb(value, rng) & 0xFFFFFF),
+            Mode::Noop => CompiledInstruction::Noop,
+        }
+    }
+}
+
 impl<'a> Instruction {
     pub fn execute<R: Rng>(
         &self,
@@ -1,6 +1,8 @@
 use enum_map::{enum_map, Enum, EnumMap};
 
-#[derive(Debug, Enum, Clone, Copy)]
+use crate::lookup;
+
+#[derive(Debug, Enum, Clone, Copy, PartialEq)]
 pub enum Molecule {
     X, // CO2
     W, // H2O, water
@@ -16,30 +18,56 @@ pub enum Molecule {
 struct MoleculeInfo {
     name: &'static str,
     maximum: i64,
+    // where the molecule is in chem space
+    coordinates: u32,
 }
 
 fn molecule_infos() -> EnumMap<Molecule, MoleculeInfo> {
-    return enum_map! {
-        Molecule::X => MoleculeInfo { name: "X", maximum: 100000 },
-        Molecule::W => MoleculeInfo { name: "W", maximum: 100000 },
-        Molecule::G => MoleculeInfo { name: "G", maximum: 10000 },
-        Molecule::O => MoleculeInfo { name: "O", maximum: 100000 },
-        Molecule::A => MoleculeInfo { name: "A", maximum: 1000 },
-        Molecule::S => MoleculeInfo { name: "S", maximum: 1000 },
-        Molecule::F => MoleculeInfo { name: "F", maximum: 1000 },
-        Molecule::B => MoleculeInfo { name: "B", maximum: 1000 },
-        Molecule::I => MoleculeInfo { name: "I", maximum: 1000 },
-    };
+    enum_map! {
+        Molecule::X => MoleculeInfo { name: "X", maximum: 100000, coordinates: 0x100000 },
+        Molecule::W => MoleculeInfo { name: "W", maximum: 100000, coordinates: 0x200000 },
+        Molecule::G => MoleculeInfo { name: "G", maximum: 10000, coordinates: 0x300000 },
+        Molecule::O => MoleculeInfo { name: "O", maximum: 100000, coordinates: 0x400000 },
+        Molecule::A => MoleculeInfo { name: "A", maximum: 1000, coordinates: 0x500000 },
+        Molecule::S => MoleculeInfo { name: "S", maximum: 1000, coordinates: 0x600000 },
+        Molecule::F => MoleculeInfo { name: "F", maximum: 1000, coordinates: 0x700000 },
+        Molecule::B => MoleculeInfo { name: "B", maximum: 1000, coordinates: 0x800000 },
+        Molecule::I => MoleculeInfo { name: "I", maximum: 1000, coordinates: 0x900000 },
+    }
+}
+
+// molecules are addressed fuzzily by their coordinates in chem space
+pub fn chem_lookup() -> lookup::Lookup<Molecule> {
+    let mut l = lookup::Lookup::<Molecule>::new();
+    for (molecule, info) in molecule_infos() {
+        l.add(info.coordinates, molecule).expect("Cannot add!");
+    }
+    l
 }
 
 type Change = i64;
 
-struct Reaction {
+pub struct Reaction {
     changes: EnumMap<Molecule, Change>,
+    // the reaction only takes place in proportion to the light
+    pub needs_light: bool,
 }
 
-fn photosynthesis() -> Reaction {
-    return Reaction {
+impl Reaction {
+    pub fn new(changes: EnumMap<Molecule, Change>) -> Reaction {
+        Reaction {
+            changes,
+            needs_light: false,
+        }
+    }
+
+    pub fn change(&self, molecule: Molecule) -> Change {
+        self.changes[molecule]
+    }
+}
+
+pub fn photosynthesis() -> Reaction {
+    Reaction {
         changes: enum_map! {
             Molecule::X => -6,
             Molecule::W => -6,
@@ -47,11 +75,12 @@ fn photosynthesis() -> Reaction {
             Molecule::O => 6,
             _ => 0
         },
-    };
+        needs_light: true,
+    }
 }
 
-fn respiration() -> Reaction {
-    return Reaction {
+pub fn respiration() -> Reaction {
+    Reaction {
         changes: enum_map! {
             Molecule::X => 6,
             Molecule::W => 6,
@@ -60,93 +89,102 @@ fn respiration() -> Reaction {
             Molecule::A => 38,
             _ => 0
         },
-    };
+        needs_light: false,
+    }
 }
 
-fn gen_starch() -> Reaction {
-    return Reaction {
+pub fn gen_starch() -> Reaction {
+    Reaction {
         changes: enum_map! {
             Molecule::G => -100,
             Molecule::A => -100,
             Molecule::S => 1,
             _ => 0
         },
-    };
+        needs_light: false,
+    }
 }
 
-fn lys_starch() -> Reaction {
-    return Reaction {
+pub fn lys_starch() -> Reaction {
+    Reaction {
         changes: enum_map! {
             Molecule::G => 100,
             Molecule::S => 1,
             _ => 0
         },
-    };
+        needs_light: false,
+    }
 }
 
-fn gen_fat() -> Reaction {
-    return Reaction {
+pub fn gen_fat() -> Reaction {
+    Reaction {
         changes: enum_map! {
             Molecule::G => -200,
             Molecule::A => -400,
             Molecule::F => 1,
             _ => 0
         },
-    };
+        needs_light: false,
+    }
 }
 
-fn lys_fat() -> Reaction {
-    return Reaction {
+pub fn lys_fat() -> Reaction {
+    Reaction {
         changes: enum_map! {
             Molecule::G => 200,
             Molecule::F => -1,
             _ => 0
         },
-    };
+        needs_light: false,
+    }
 }
 
-fn gen_barrier() -> Reaction {
-    return Reaction {
+pub fn gen_barrier() -> Reaction {
+    Reaction {
         changes: enum_map! {
             Molecule::G => -2,
             Molecule::A => -10,
             Molecule::B => 1,
             _ => 0
         },
-    };
+        needs_light: false,
+    }
 }
 
-fn lys_barrier() -> Reaction {
-    return Reaction {
+pub fn lys_barrier() -> Reaction {
+    Reaction {
         changes: enum_map! {
             Molecule::G => 2,
             Molecule::A => -10,
             Molecule::B => -1,
             _ => 0
         },
-    };
+        needs_light: false,
+    }
 }
 
-fn gen_instruction() -> Reaction {
-    return Reaction {
+pub fn gen_instruction() -> Reaction {
+    Reaction {
         changes: enum_map! {
             Molecule::G => -2,
             Molecule::A => -10,
             Molecule::I => 1,
             _ => 0
         },
-    };
+        needs_light: false,
+    }
 }
 
-fn lys_instruction() -> Reaction {
-    return Reaction {
+pub fn lys_instruction() -> Reaction {
+    Reaction {
         changes: enum_map! {
             Molecule::G => 2,
             Molecule::A => -10,
             Molecule::I => -1,
             _ => 0
         },
-    };
+        needs_light: false,
+    }
 }
 
 pub struct Pool {
@@ -154,8 +192,77 @@ pub struct Pool {
     molecule_infos: EnumMap<Molecule, MoleculeInfo>,
 }
 
+impl Default for Pool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Pool {
-    fn can_apply(&self, reaction: &Reaction) -> bool {
+    pub fn new() -> Pool {
+        Pool {
+            molecule_amounts: enum_map! { _ => 0 },
+            molecule_infos: molecule_infos(),
+        }
+    }
+
+    // a pool without maximum amounts, such as the environment
+    pub fn unbounded() -> Pool {
+        let mut molecule_infos = molecule_infos();
+        for (_, info) in molecule_infos.iter_mut() {
+            info.maximum = i64::MAX;
+        }
+        Pool {
+            molecule_amounts: enum_map! { _ => 0 },
+            molecule_infos,
+        }
+    }
+
+    pub fn amounts(&self) -> impl Iterator<Item = (Molecule, i64)> + '_ {
+        self.molecule_amounts
+            .iter()
+            .map(|(molecule, &amount)| (molecule, amount))
+    }
+
+    pub fn amount(&self, molecule: Molecule) -> i64 {
+        self.molecule_amounts[molecule]
+    }
+
+    pub fn set_amount(&mut self, molecule: Molecule, amount: i64) {
+        self.molecule_amounts[molecule] = amount.clamp(0, self.maximum(molecule));
+    }
+
+    // add an amount of molecule, up to the maximum
+    pub fn add(&mut self, molecule: Molecule, amount: i64) {
+        self.set_amount(
+            molecule,
+            self.molecule_amounts[molecule].saturating_add(amount),
+        );
+    }
+
+    pub fn consume(&mut self, molecule: Molecule, amount: i64) -> bool {
+        if self.molecule_amounts[molecule] < amount {
+            return false;
+        }
+        self.molecule_amounts[molecule] -= amount;
+        true
+    }
+
+    // whether amount of molecule can move from this pool into target
+    pub fn can_transfer(&self, target: &Pool, molecule: Molecule, amount: i64) -> bool {
+        self.amount(molecule) >= amount
+            && target.amount(molecule) <= target.maximum(molecule) - amount
+    }
+
+    pub fn name(&self, molecule: Molecule) -> &'static str {
+        self.molecule_infos[molecule].name
+    }
+
+    pub fn maximum(&self, molecule: Molecule) -> i64 {
+        self.molecule_infos[molecule].maximum
+    }
+
+    pub fn can_apply(&self, reaction: &Reaction) -> bool {
         for (molecule, &change) in reaction.changes.iter() {
             if change > 0 {
                 if (self.molecule_amounts[molecule] + change)
@@ -169,17 +276,17 @@ impl Pool {
                 }
             }
         }
-        return true;
+        true
     }
 
-    fn apply(&mut self, reaction: &Reaction) -> bool {
+    pub fn apply(&mut self, reaction: &Reaction) -> bool {
         if !self.can_apply(reaction) {
             return false;
         }
         for (molecule, change) in reaction.changes.iter() {
             self.molecule_amounts[molecule] += change;
         }
-        return true;
+        true
     }
 }
 
@@ -201,7 +308,7 @@ mod tests {
             molecule_infos: molecule_infos(),
         };
         let r = pool.apply(&photosynthesis());
-        assert_eq!(r, true);
+        assert!(r);
         assert_eq!(pool.molecule_amounts[Molecule::X], 4);
         assert_eq!(pool.molecule_amounts[Molecule::W], 4);
         assert_eq!(pool.molecule_amounts[Molecule::G], 1);
@@ -222,7 +329,7 @@ mod tests {
             molecule_infos: molecule_infos(),
         };
         let r = pool.apply(&photosynthesis());
-        assert_eq!(r, false);
+        assert!(!r);
         assert_eq!(pool.molecule_amounts[Molecule::X], 5);
         assert_eq!(pool.molecule_amounts[Molecule::W], 5);
         assert_eq!(pool.molecule_amounts[Molecule::G], 0);
@@ -243,7 +350,7 @@ mod tests {
             molecule_infos: molecule_infos(),
         };
         let r = pool.apply(&photosynthesis());
-        assert_eq!(r, false);
+        assert!(!r);
         assert_eq!(pool.molecule_amounts[Molecule::X], 10);
         assert_eq!(pool.molecule_amounts[Molecule::W], 5);
         assert_eq!(pool.molecule_amounts[Molecule::G], 0);
@@ -264,10 +371,91 @@ mod tests {
             molecule_infos: molecule_infos(),
         };
         let r = pool.apply(&photosynthesis());
-        assert_eq!(r, false);
+        assert!(!r);
         assert_eq!(pool.molecule_amounts[Molecule::X], 10);
         assert_eq!(pool.molecule_amounts[Molecule::W], 10);
         assert_eq!(pool.molecule_amounts[Molecule::G], 20000);
         assert_eq!(pool.molecule_amounts[Molecule::O], 0);
     }
+
+    #[test]
+    fn test_new_pool_is_empty() {
+        let pool = Pool::new();
+        assert_eq!(pool.amount(Molecule::X), 0);
+        assert_eq!(pool.amount(Molecule::A), 0);
+        assert_eq!(pool.name(Molecule::A), "A");
+    }
+
+    #[test]
+    fn test_set_amount() {
+        let mut pool = Pool::new();
+        pool.set_amount(Molecule::G, 10);
+        assert_eq!(pool.amount(Molecule::G), 10);
+        pool.set_amount(Molecule::G, 20000);
+        assert_eq!(pool.amount(Molecule::G), 10000);
+        pool.set_amount(Molecule::G, -1);
+        assert_eq!(pool.amount(Molecule::G), 0);
+    }
+
+    #[test]
+    fn test_add() {
+        let mut pool = Pool::new();
+        pool.add(Molecule::A, 5);
+        pool.add(Molecule::A, 5);
+        assert_eq!(pool.amount(Molecule::A), 10);
+        pool.add(Molecule::A, 5000);
+        assert_eq!(pool.amount(Molecule::A), 1000);
+    }
+
+    #[test]
+    fn test_add_unbounded() {
+        let mut pool = Pool::unbounded();
+        pool.add(Molecule::A, 5000);
+        assert_eq!(pool.amount(Molecule::A), 5000);
+    }
+
+    #[test]
+    fn test_consume() {
+        let mut pool = Pool::new();
+        pool.set_amount(Molecule::A, 5);
+        assert!(pool.consume(Molecule::A, 3));
+        assert!(!pool.consume(Molecule::A, 3));
+        assert_eq!(pool.amount(Molecule::A), 2);
+    }
+
+    #[test]
+    fn test_apply_new_reaction() {
+        let mut pool = Pool::new();
+        pool.set_amount(Molecule::A, 5);
+        let reaction = Reaction::new(enum_map! {
+            Molecule::A => -2,
+            Molecule::B => 1,
+            _ => 0
+        });
+        assert!(pool.apply(&reaction));
+        assert!(pool.apply(&reaction));
+        assert!(!pool.apply(&reaction));
+        assert_eq!(pool.amount(Molecule::A), 1);
+        assert_eq!(pool.amount(Molecule::B), 2);
+    }
+
+    #[test]
+    fn test_chem_lookup() {
+        let l = chem_lookup();
+        assert_eq!(l.find(0x300000), Some(&Molecule::G));
+        assert_eq!(l.find(0x310203), Some(&Molecule::G));
+        assert_eq!(l.find(0x000000), Some(&Molecule::X));
+    }
+
+    #[test]
+    fn test_can_transfer() {
+        let mut pool = Pool::new();
+        pool.set_amount(Molecule::A, 1000);
+        let mut target = Pool::new();
+        assert!(pool.can_transfer(&target, Molecule::A, 10));
+        assert!(!pool.can_transfer(&target, Molecule::G, 10));
+        target.set_amount(Molecule::A, 995);
+        assert!(!pool.can_transfer(&target, Molecule::A, 10));
+        assert!(pool.can_transfer(&Pool::unbounded(), Molecule::A, 10));
+    }
 }
@@ -0,0 +1,165 @@
+// Smells are traces left at locations by cells. They spread to
+// neighboring locations and slowly fade away.
+use crate::grid::{Direction, Grid, Position};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ScentChannel {
+    // the fraction of a scent that spreads to neighboring locations
+    pub diffusion: f64,
+    // the fraction of a scent that disappears
+    pub decay: f64,
+}
+
+pub struct Scents {
+    width: usize,
+    // amounts per channel per location
+    amounts: Vec<Vec<f64>>,
+}
+
+impl Scents {
+    pub fn new(width: usize, height: usize, channels: usize) -> Scents {
+        Scents {
+            width,
+            amounts: vec![vec![0.0; width * height]; channels],
+        }
+    }
+
+    fn index(&self, position: Position) -> usize {
+        let (x, y) = position;
+        y * self.width + x
+    }
+
+    pub fn channels(&self) -> usize {
+        self.amounts.len()
+    }
+
+    pub fn amount(&self, channel: usize, position: Position) -> f64 {
+        self.amounts[channel][self.index(position)]
+    }
+
+    pub fn emit(&mut self, channel: usize, position: Position, amount: f64) {
+        let index = self.index(position);
+        self.amounts[channel][index] += amount;
+    }
+
+    // diffuse and then decay every channel
+    pub fn update(&mut self, grid: &Grid, channels: &[ScentChannel]) {
+        for (amounts, channel) in self.amounts.iter_mut().zip(channels) {
+            diffuse(amounts, grid, channel.diffusion);
+            for amount in amounts.iter_mut() {
+                *amount *= 1.0 - channel.decay;
+            }
+        }
+    }
+}
+
+fn diffuse(amounts: &mut [f64], grid: &Grid, rate: f64) {
+    if rate == 0.0 {
+        return;
+    }
+    let mut deltas = vec![0.0; amounts.len()];
+    for (index, &amount) in amounts.iter().enumerate() {
+        if amount == 0.0 {
+            continue;
+        }
+        let position = (index % grid.width, index / grid.width);
+        let share = amount * rate / 4.0;
+        for &direction in Direction::all().iter() {
+            if let Some((x, y)) = grid.neighbor_position(position, direction) {
+                deltas[y * grid.width + x] += share;
+                deltas[index] -= share;
+            }
+        }
+    }
+    for (amount, delta) in amounts.iter_mut().zip(deltas) {
+        *amount += delta;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Topology;
+
+    fn total(scents: &Scents, channel: usize, grid: &Grid) -> f64 {
+        let mut total = 0.0;
+        for y in 0..grid.height {
+            for x in 0..grid.width {
+                total += scents.amount(channel, (x, y));
+            }
+        }
+        total
+    }
+
+    #[test]
+    fn test_emit() {
+        let mut scents = Scents::new(3, 3, 2);
+        scents.emit(1, (1, 2), 5.0);
+        scents.emit(1, (1, 2), 5.0);
+        assert_eq!(scents.amount(1, (1, 2)), 10.0);
+        assert_eq!(scents.amount(0, (1, 2)), 0.0);
+    }
+
+    #[test]
+    fn test_decay() {
+        let grid = Grid::new(3, 3, Topology::Bounded);
+        let mut scents = Scents::new(3, 3, 2);
+        let channels = [
+            ScentChannel {
+                diffusion: 0.0,
+                decay: 0.5,
+            },
+            ScentChannel {
+                diffusion: 0.0,
+                decay: 0.1,
+            },
+        ];
+        scents.emit(0, (1, 1), 100.0);
+        scents.emit(1, (1, 1), 100.0);
+        scents.update(&grid, &channels);
+        assert_eq!(scents.amount(0, (1, 1)), 50.0);
+        scents.update(&grid, &channels);
+        assert_eq!(scents.amount(0, (1, 1)), 25.0);
+        for _ in 0..8 {
+            scents.update(&grid, &channels);
+        }
+        assert!((scents.amount(1, (1, 1)) - 100.0 * 0.9f64.powi(10)).abs() < 1e-9);
+        // faster decaying channels fade first
+        assert!(scents.amount(0, (1, 1)) < scents.amount(1, (1, 1)));
+    }
+
+    #[test]
+    fn test_decay_with_diffusion() {
+        let grid = Grid::new(5, 5, Topology::Toroidal);
+        let mut scents = Scents::new(5, 5, 1);
+        let channels = [ScentChannel {
+            diffusion: 0.3,
+            decay: 0.2,
+        }];
+        scents.emit(0, (2, 2), 100.0);
+        for _ in 0..5 {
+            scents.update(&grid, &channels);
+        }
+        assert!((total(&scents, 0, &grid) - 100.0 * 0.8f64.powi(5)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_diffusion() {
+        let grid = Grid::new(3, 3, Topology::Bounded);
+        let mut scents = Scents::new(3, 3, 1);
+        let channels = [ScentChannel {
+            diffusion: 0.4,
+            decay: 0.0,
+        }];
+        scents.emit(0, (0, 0), 100.0);
+        scents.update(&grid, &channels);
+        // only two neighbors in the corner
+        assert_eq!(scents.amount(0, (0, 0)), 80.0);
+        assert_eq!(scents.amount(0, (1, 0)), 10.0);
+        assert_eq!(scents.amount(0, (0, 1)), 10.0);
+        assert_eq!(scents.amount(0, (1, 1)), 0.0);
+        scents.update(&grid, &channels);
+        assert!(scents.amount(0, (1, 1)) > 0.0);
+        assert!((total(&scents, 0, &grid) - 100.0).abs() < 1e-9);
+    }
+}
@@ -9,9 +9,8 @@ pub trait Stack<T> {
 
 impl<T> Stack<T> for Vec<T> {
     fn pop2(&mut self) -> Option<(T, T)> {
-        return self
-            .pop()
-            .and_then(|first| self.pop().and_then(|second| Some((second, first))));
+        self.pop()
+            .and_then(|first| self.pop().map(|second| (second, first)))
     }
 }
 
@@ -27,14 +26,13 @@ where
     F: FnOnce(T, T) -> Option<T>,
 {
     fn op2(&mut self, op: F) -> Option<()> {
-        return self.pop2().and_then(|(x, y)| op(x, y)).and_then(|result| {
+        self.pop2().and_then(|(x, y)| op(x, y)).map(|result| {
             self.push(result);
-            return Some(());
-        });
+        })
     }
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum Instruction {
     Add = 0x000100,
     Sub = 0x000110,
@@ -71,20 +69,17 @@ impl Instruction {
             Instruction::Or => {
                 stack.op2(|first, second| Some(bool_to_nr(nr_to_bool(first) || nr_to_bool(second))))
             }
-            Instruction::Not => stack.pop().and_then(|v| {
+            Instruction::Not => stack.pop().map(|v| {
                 stack.push(bool_to_nr(!nr_to_bool(v)));
-                return Some(());
             }),
-            Instruction::Dup => stack.pop().and_then(|v| {
+            Instruction::Dup => stack.pop().map(|v| {
                 stack.push(v);
                 stack.push(v);
-                return Some(());
             }),
-            Instruction::Drop => stack.pop().and_then(|_v| return Some(())),
-            Instruction::Swap => stack.pop2().and_then(|(x, y)| {
+            Instruction::Drop => stack.pop().map(|_v| ()),
+            Instruction::Swap => stack.pop2().map(|(x, y)| {
                 stack.push(y);
                 stack.push(x);
-                return Some(());
             }),
             Instruction::Over => {
                 if stack.len() < 2 {
@@ -92,7 +87,7 @@ impl Instruction {
                     return None;
                 }
                 stack.push(stack[stack.len() - 2]);
-                return Some(());
+                Some(())
             }
             Instruction::Rot => {
                 if stack.len() < 3 {
@@ -106,7 +101,7 @@ impl Instruction {
                 stack.push(b);
                 stack.push(a);
                 stack.push(c);
-                return Some(());
+                Some(())
             }
         }
     }
@@ -114,9 +109,30 @@ impl Instruction {
     pub fn coordinates(&self) -> u32 {
         *self as u32
     }
+
+    pub fn all() -> [Instruction; 16] {
+        [
+            Instruction::Add,
+            Instruction::Sub,
+            Instruction::Mul,
+            Instruction::Div,
+            Instruction::Dup,
+            Instruction::Drop,
+            Instruction::Swap,
+            Instruction::Over,
+            Instruction::Rot,
+            Instruction::Eq,
+            Instruction::Ne,
+            Instruction::Gt,
+            Instruction::Lt,
+            Instruction::And,
+            Instruction::Or,
+            Instruction::Not,
+        ]
+    }
 }
 
-fn bool_to_nr(b: bool) -> u32 {
+pub fn bool_to_nr(b: bool) -> u32 {
     if b {
         TRUE
     } else {
@@ -142,7 +158,7 @@ mod tests {
 
     #[test]
     fn test_add_execute_overflow() {
-        let mut s: Vec<u32> = vec![u32::max_value(), 1];
+        let mut s: Vec<u32> = vec![u32::MAX, 1];
         let b = Instruction::Add.execute(&mut s);
         assert!(b.is_none());
         assert_eq!(s, []);
@@ -0,0 +1,262 @@
+// Tasks generate the problems that catalyst ports pose to a cell.
+use rand::distributions::{Distribution, Uniform};
+use rand::{Rng, RngCore};
+
+use crate::stack;
+use crate::stack::bool_to_nr;
+
+// how often a task tries to generate a problem that works before it falls
+// back to one that always does
+const MAX_TRIES: usize = 100;
+
+pub trait Task {
+    // generate input and expected output
+    fn generate(&self, rng: &mut dyn RngCore) -> (Vec<u32>, Vec<u32>);
+}
+
+// Always the same problem.
+pub struct Fixed {
+    input: Vec<u32>,
+    expected_output: Vec<u32>,
+}
+
+impl Fixed {
+    pub fn new(input: &[u32], expected_output: &[u32]) -> Fixed {
+        Fixed {
+            input: input.to_vec(),
+            expected_output: expected_output.to_vec(),
+        }
+    }
+}
+
+impl Task for Fixed {
+    fn generate(&self, _rng: &mut dyn RngCore) -> (Vec<u32>, Vec<u32>) {
+        (self.input.clone(), self.expected_output.clone())
+    }
+}
+
+// Apply a stack instruction to two numbers up to maximum. Works for the
+// arithmetic (Add, Sub, Mul, Div) and comparison (Eq, Ne, Gt, Lt)
+// instructions. Combinations that fail, such as division by zero, are
+// not generated.
+pub struct Operator {
+    instruction: stack::Instruction,
+    maximum: u32,
+}
+
+impl Operator {
+    // None if the instruction is not supported, or if no problem up to
+    // maximum can work
+    pub fn new(instruction: stack::Instruction, maximum: u32) -> Option<Operator> {
+        match instruction {
+            stack::Instruction::Add
+            | stack::Instruction::Sub
+            | stack::Instruction::Mul
+            | stack::Instruction::Eq
+            | stack::Instruction::Ne
+            | stack::Instruction::Gt
+            | stack::Instruction::Lt => {}
+            // there is nothing to divide by
+            stack::Instruction::Div if maximum > 0 => {}
+            _ => return None,
+        }
+        Some(Operator {
+            instruction,
+            maximum,
+        })
+    }
+
+    fn apply(&self, input: Vec<u32>) -> Option<(Vec<u32>, Vec<u32>)> {
+        let mut stack = input.clone();
+        self.instruction.execute(&mut stack)?;
+        Some((input, stack))
+    }
+}
+
+impl Task for Operator {
+    fn generate(&self, rng: &mut dyn RngCore) -> (Vec<u32>, Vec<u32>) {
+        let range = Uniform::new_inclusive(0, self.maximum);
+        (0..MAX_TRIES)
+            .find_map(|_| self.apply(vec![range.sample(rng), range.sample(rng)]))
+            .unwrap_or_else(|| {
+                // 1 and 1 (or 0 and 0) work for every supported instruction
+                let value = self.maximum.min(1);
+                self.apply(vec![value, value])
+                    .expect("Fallback problem cannot fail!")
+            })
+    }
+}
+
+// Apply a boolean stack instruction (And, Or, Not) to TRUE and FALSE
+// values.
+pub struct Boolean {
+    instruction: stack::Instruction,
+}
+
+impl Boolean {
+    // None if the instruction is not a boolean instruction
+    pub fn new(instruction: stack::Instruction) -> Option<Boolean> {
+        match instruction {
+            stack::Instruction::And | stack::Instruction::Or | stack::Instruction::Not => {
+                Some(Boolean { instruction })
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Task for Boolean {
+    fn generate(&self, rng: &mut dyn RngCore) -> (Vec<u32>, Vec<u32>) {
+        let amount = match self.instruction {
+            stack::Instruction::Not => 1,
+            _ => 2,
+        };
+        let input: Vec<u32> = (0..amount).map(|_| bool_to_nr(rng.gen())).collect();
+        let mut stack = input.clone();
+        // boolean instructions cannot fail with enough values on the stack
+        self.instruction
+            .execute(&mut stack)
+            .expect("Boolean instruction failed!");
+        (input, stack)
+    }
+}
+
+// Raise a base to an exponent: a b -- a^b
+pub struct Exponential {
+    maximum_base: u32,
+    maximum_exponent: u32,
+}
+
+impl Exponential {
+    pub fn new(maximum_base: u32, maximum_exponent: u32) -> Exponential {
+        Exponential {
+            maximum_base,
+            maximum_exponent,
+        }
+    }
+}
+
+impl Task for Exponential {
+    fn generate(&self, rng: &mut dyn RngCore) -> (Vec<u32>, Vec<u32>) {
+        let bases = Uniform::new_inclusive(0, self.maximum_base);
+        let exponents = Uniform::new_inclusive(0, self.maximum_exponent);
+        (0..MAX_TRIES)
+            .find_map(|_| {
+                let base = bases.sample(rng);
+                let exponent = exponents.sample(rng);
+                base.checked_pow(exponent)
+                    .map(|result| (vec![base, exponent], vec![result]))
+            })
+            // anything to the power of 0 is 1
+            .unwrap_or_else(|| (vec![self.maximum_base, 0], vec![1]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    const SEED: [u8; 16] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+
+    #[test]
+    fn test_fixed() {
+        let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+        let task = Fixed::new(&[1, 2], &[3]);
+        assert_eq!(task.generate(&mut rng), (vec![1, 2], vec![3]));
+    }
+
+    #[test]
+    fn test_operator_add() {
+        let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+        let task = Operator::new(stack::Instruction::Add, 10).unwrap();
+        for _ in 0..100 {
+            let (input, output) = task.generate(&mut rng);
+            assert!(input[0] <= 10 && input[1] <= 10);
+            assert_eq!(output, [input[0] + input[1]]);
+        }
+    }
+
+    #[test]
+    fn test_operator_div_no_failure() {
+        let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+        let task = Operator::new(stack::Instruction::Div, 3).unwrap();
+        for _ in 0..100 {
+            let (input, output) = task.generate(&mut rng);
+            assert_ne!(input[1], 0);
+            assert_eq!(output, [input[0] / input[1]]);
+        }
+    }
+
+    #[test]
+    fn test_operator_eq() {
+        let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+        let task = Operator::new(stack::Instruction::Eq, 1).unwrap();
+        for _ in 0..100 {
+            let (input, output) = task.generate(&mut rng);
+            assert_eq!(output, [bool_to_nr(input[0] == input[1])]);
+        }
+    }
+
+    #[test]
+    fn test_operator_invalid() {
+        assert!(Operator::new(stack::Instruction::Rot, 10).is_none());
+        assert!(Operator::new(stack::Instruction::Not, 10).is_none());
+        assert!(Operator::new(stack::Instruction::Div, 0).is_none());
+    }
+
+    #[test]
+    fn test_operator_full_range() {
+        let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+        let task = Operator::new(stack::Instruction::Add, u32::MAX).unwrap();
+        for _ in 0..100 {
+            let (input, output) = task.generate(&mut rng);
+            assert_eq!(output, [input[0] + input[1]]);
+        }
+    }
+
+    #[test]
+    fn test_operator_fallback() {
+        let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+        // two random numbers this large practically never multiply
+        let task = Operator::new(stack::Instruction::Mul, u32::MAX).unwrap();
+        assert_eq!(task.generate(&mut rng), (vec![1, 1], vec![1]));
+    }
+
+    #[test]
+    fn test_boolean_invalid() {
+        assert!(Boolean::new(stack::Instruction::Add).is_none());
+    }
+
+    #[test]
+    fn test_boolean_and() {
+        let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+        let task = Boolean::new(stack::Instruction::And).unwrap();
+        for _ in 0..100 {
+            let (input, output) = task.generate(&mut rng);
+            assert_eq!(input.len(), 2);
+            assert_eq!(output, [bool_to_nr(input[0] != 0 && input[1] != 0)]);
+        }
+    }
+
+    #[test]
+    fn test_boolean_not() {
+        let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+        let task = Boolean::new(stack::Instruction::Not).unwrap();
+        for _ in 0..100 {
+            let (input, output) = task.generate(&mut rng);
+            assert_eq!(input.len(), 1);
+            assert_eq!(output, [bool_to_nr(input[0] == 0)]);
+        }
+    }
+
+    #[test]
+    fn test_exponential() {
+        let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+        let task = Exponential::new(5, 3);
+        for _ in 0..100 {
+            let (input, output) = task.generate(&mut rng);
+            assert_eq!(output, [input[0].pow(input[1])]);
+        }
+    }
+}
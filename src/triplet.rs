@@ -18,10 +18,7 @@ pub struct Triplet {
 impl Triplet {
     pub fn from_int(i: u32) -> Triplet {
         let fields = i >> 24 & 0xff;
-        // XXX this isn't correct yet. It needs to use
-        // another mask FF and declare all numbers noop above
-        // call. We need tests for that.
-        let mode = match fields & 0x01 {
+        let mode = match fields {
             0 => Mode::Number,
             1 => Mode::Instruction,
             2 => Mode::Call,
@@ -31,10 +28,9 @@ impl Triplet {
             r: (i >> 16 & 0xff) as u8,
             g: (i >> 8 & 0xff) as u8,
             b: (i & 0xff) as u8,
-            mode: mode,
+            mode,
         }
     }
-
 }
 
 #[cfg(test)]
@@ -71,4 +67,26 @@ mod tests {
         assert_eq!(t.mode, Mode::Instruction)
     }
 
+    #[test]
+    fn test_from_int_call() {
+        let t = Triplet::from_int(0x02010203);
+
+        assert_eq!(t.r, 1);
+        assert_eq!(t.g, 2);
+        assert_eq!(t.b, 3);
+        assert_eq!(t.mode, Mode::Call)
+    }
+
+    #[test]
+    fn test_from_int_noop() {
+        assert_eq!(Triplet::from_int(0x03010203).mode, Mode::Noop);
+        assert_eq!(Triplet::from_int(0xFF010203).mode, Mode::Noop);
+    }
+
+    #[test]
+    fn test_from_int_only_instruction_bit() {
+        // higher modes are not decoded from the lowest bit alone
+        assert_eq!(Triplet::from_int(0x03000000).mode, Mode::Noop);
+        assert_eq!(Triplet::from_int(0x02000000).mode, Mode::Call);
+    }
 }
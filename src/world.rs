@@ -4,11 +4,18 @@ use std::collections::HashMap;
 
 use crate::cell::{Cell, CellKey};
 use crate::gene::{Gene, GeneKey};
-use crate::processor::{Action, Config, Processor, ProcessorKey};
+use crate::grid::{Direction, Grid, Position};
+use crate::lookup::Lookup;
+use crate::organelle::{Connection, Gate, Organelle};
+use crate::processor::{Action, Config, Instruction, Processor, ProcessorKey};
+use crate::reaction::Molecule;
+use crate::scent::Scents;
 
 pub struct Entities {
     pub cells: DenseSlotMap<CellKey, Cell>,
     pub genes: DenseSlotMap<GeneKey, Gene>,
+    pub grid: Grid,
+    pub scents: Scents,
     gene_by_id: HashMap<u32, GeneKey>,
 }
 
@@ -16,6 +23,7 @@ pub struct World {
     pub entities: Entities,
     pub config: Config,
     pub processors: DenseSlotMap<ProcessorKey, Processor>,
+    tick: u64,
 }
 
 impl World {
@@ -24,16 +32,154 @@ impl World {
             entities: Entities {
                 cells: DenseSlotMap::with_key(),
                 genes: DenseSlotMap::with_key(),
-
+                grid: Grid::new(config.width, config.height, config.topology),
+                scents: Scents::new(config.width, config.height, config.scent_channels.len()),
                 gene_by_id: HashMap::new(),
             },
             processors: DenseSlotMap::with_key(),
-            config: config,
+            config,
+            tick: 0,
+        }
+    }
+
+    // a cell at the first free position, if the grid is not full
+    pub fn create_cell(&mut self) -> Option<CellKey> {
+        let position = self.entities.grid.free_position()?;
+        self.create_cell_at(position)
+    }
+
+    // a cell placed by the host gets its wall for free
+    pub fn create_cell_at(&mut self, position: Position) -> Option<CellKey> {
+        let cell_key = self.entities.create_cell_at(position)?;
+        self.entities.cells[cell_key]
+            .pool
+            .set_amount(Molecule::B, self.config.initial_wall);
+        Some(cell_key)
+    }
+
+    pub fn remove_cell(&mut self, cell_key: CellKey) {
+        self.processors
+            .retain(|_, processor| processor.cell_key() != cell_key);
+        self.entities.remove_cell(cell_key);
+    }
+
+    // the cell is removed and its materials end up in the environment
+    pub fn kill_cell(&mut self, cell_key: CellKey) {
+        if let Some(position) = self.entities.grid.position(cell_key) {
+            let cell = &self.entities.cells[cell_key];
+            let instructions: usize = cell
+                .gene_keys()
+                .map(|gene_key| self.entities.genes[gene_key].code().len())
+                .sum();
+            let environment = self.entities.grid.environment_mut(position);
+            for (molecule, amount) in cell.pool.amounts() {
+                // ATP has no long term existence and dissipates
+                if molecule != Molecule::A {
+                    environment.add(molecule, amount);
+                }
+            }
+            environment.add(Molecule::I, instructions as i64);
+            environment.add(Molecule::B, cell.barrier());
+        }
+        self.remove_cell(cell_key);
+    }
+
+    pub fn add_organelle(
+        &mut self,
+        cell_key: CellKey,
+        coordinates: u32,
+        organelle: Organelle,
+    ) -> usize {
+        self.entities.cells[cell_key].add_organelle(coordinates, organelle)
+    }
+
+    pub fn environment_amount(&self, position: Position, molecule: Molecule) -> i64 {
+        self.entities.grid.environment(position).amount(molecule)
+    }
+
+    pub fn set_environment_amount(&mut self, position: Position, molecule: Molecule, amount: i64) {
+        self.entities
+            .grid
+            .environment_mut(position)
+            .set_amount(molecule, amount);
+    }
+
+    pub fn cell_position(&self, cell_key: CellKey) -> Option<Position> {
+        self.entities.grid.position(cell_key)
+    }
+
+    pub fn cell_at(&self, position: Position) -> Option<CellKey> {
+        self.entities.grid.get(position)
+    }
+
+    pub fn neighbor(&self, cell_key: CellKey, direction: Direction) -> Option<CellKey> {
+        self.entities.grid.neighbor(cell_key, direction)
+    }
+
+    pub fn molecule_amount(&self, cell_key: CellKey, molecule: Molecule) -> i64 {
+        self.entities.cells[cell_key].pool.amount(molecule)
+    }
+
+    pub fn set_molecule_amount(&mut self, cell_key: CellKey, molecule: Molecule, amount: i64) {
+        self.entities.cells[cell_key]
+            .pool
+            .set_amount(molecule, amount);
+    }
+
+    // Connect a cell with its neighbor in direction, adding connection
+    // organelles on both sides if needed. Both sides start out open.
+    pub fn connect(&mut self, cell_key: CellKey, direction: Direction) -> bool {
+        let neighbor_key = match self.entities.grid.neighbor(cell_key, direction) {
+            Some(neighbor_key) => neighbor_key,
+            None => return false,
+        };
+        self.connect_side(cell_key, direction, neighbor_key);
+        self.connect_side(neighbor_key, direction.opposite(), cell_key);
+        true
+    }
+
+    // Move a cell to a free position. It leaves its connections behind.
+    pub fn move_cell(&mut self, cell_key: CellKey, position: Position) -> bool {
+        if !self.entities.grid.is_free(position) {
+            return false;
+        }
+        self.disconnect(cell_key);
+        self.entities.grid.remove(cell_key);
+        self.entities.grid.insert(cell_key, position)
+    }
+
+    // Break all connections of a cell, on both sides.
+    pub fn disconnect(&mut self, cell_key: CellKey) {
+        let cells = &mut self.entities.cells;
+        for &direction in Direction::all().iter() {
+            let peer_key = match cells[cell_key].connection_mut(direction) {
+                Some(connection) => connection.peer.take(),
+                None => None,
+            };
+            let peer_connection = peer_key
+                .and_then(|peer_key| cells.get_mut(peer_key))
+                .and_then(|peer| peer.connection_mut(direction.opposite()));
+            if let Some(peer_connection) = peer_connection {
+                if peer_connection.peer == Some(cell_key) {
+                    peer_connection.peer = None;
+                }
+            }
         }
     }
 
-    pub fn create_cell(&mut self) -> CellKey {
-        self.entities.cells.insert(Cell::new())
+    fn connect_side(&mut self, cell_key: CellKey, direction: Direction, peer_key: CellKey) {
+        let coordinates = self.config.connection_coordinates[direction as usize];
+        let cell = &mut self.entities.cells[cell_key];
+        if cell.connection(direction).is_none() {
+            cell.add_organelle(
+                coordinates,
+                Organelle::Connection(Connection::new(direction)),
+            );
+        }
+        let connection = cell.connection_mut(direction).unwrap();
+        connection.peer = Some(peer_key);
+        connection.set_open(Gate::Gene, true);
+        connection.set_open(Gate::Chem, true);
     }
 
     pub fn create_gene_in_cell<R: Rng>(
@@ -42,40 +188,284 @@ impl World {
         code: &[u32],
         rng: &mut R,
     ) -> GeneKey {
-        self.entities.create_gene_in_cell(cell_key, code, rng)
+        self.entities
+            .create_gene_in_cell(cell_key, code, &self.config.instruction_lookup, rng)
     }
 
     pub fn create_gene(&mut self, code: &[u32]) -> GeneKey {
-        self.entities.genes.insert(Gene::new(0, code))
+        self.entities
+            .genes
+            .insert(Gene::new(0, code, &self.config.instruction_lookup))
     }
 
     pub fn create_processor(&mut self, cell_key: CellKey, gene_key: GeneKey) -> ProcessorKey {
         self.processors.insert(Processor::new(cell_key, gene_key))
     }
 
-    pub fn execute_processor<R: Rng>(
-        processor: &mut Processor,
-        entities: &mut Entities,
-        config: &Config,
-        rng: &mut R,
-    ) {
-        let action = processor.execute(entities, config, rng);
+    pub fn execute_processor<R: Rng>(&mut self, processor_key: ProcessorKey, rng: &mut R) {
+        let processor = match self.processors.get_mut(processor_key) {
+            Some(processor) => processor,
+            None => return,
+        };
+        // the instruction is chosen before it is paid for, so that a
+        // perturbed instruction costs what it is
+        let compiled = processor.next_instruction(&self.entities, &self.config, rng);
+        // a processor stalls if its cell cannot pay for the next instruction.
+        // the cost is capped at what a cell can hold, so that a processor
+        // with huge stacks cannot stall forever
+        let cost = processor.cost(compiled, &self.config);
+        let pool = &mut self.entities.cells[processor.cell_key()].pool;
+        if !pool.consume(Molecule::A, cost.min(pool.maximum(Molecule::A))) {
+            return;
+        }
+        let action = processor.execute(compiled, &self.entities, &self.config, rng);
         match action {
             Some(Action::Noop) => {}
             Some(Action::GeneWrite(gene_key, value)) => {
-                let gene = &mut entities.genes[gene_key];
-                gene.code.push(value);
+                let cell_key = self.processors[processor_key].cell_key();
+                self.entities.cells[cell_key].pool.consume(Molecule::I, 1);
+                self.entities.genes[gene_key].write(value, &self.config.instruction_lookup);
             }
             Some(Action::GeneCreate(cell_key, id)) => {
-                entities.create_gene_in_cell_with_id(id, cell_key, &[0]);
+                self.entities.cells[cell_key].pool.consume(Molecule::I, 1);
+                self.entities.create_gene_in_cell_with_id(
+                    id,
+                    cell_key,
+                    &[0],
+                    &self.config.instruction_lookup,
+                );
+            }
+            Some(Action::ProcCreate(cell_key, gene_key, pc)) => {
+                if self.gene_processors_amount(gene_key) >= self.config.max_processors_per_gene {
+                    self.processors[processor_key].failures += 1;
+                    return;
+                }
+                self.processors
+                    .insert(Processor::new_at(cell_key, gene_key, pc));
+            }
+            Some(Action::ProcDestroy) => {
+                self.processors.remove(processor_key);
+            }
+            Some(Action::ProcEnd(gene_key)) => {
+                self.processors
+                    .retain(|_, processor| processor.start_gene_key() != gene_key);
+            }
+            Some(Action::CellCreate(cell_key, direction, position)) => {
+                self.entities.cells[cell_key]
+                    .pool
+                    .apply(&self.config.cell_create_cost);
+                // the new cell gets its wall from its parent, and starts
+                // connected to it
+                if let Some(new_cell_key) = self.entities.create_cell_at(position) {
+                    let wall = self.config.initial_wall;
+                    self.entities.cells[cell_key]
+                        .pool
+                        .consume(Molecule::B, wall);
+                    self.entities.cells[new_cell_key]
+                        .pool
+                        .set_amount(Molecule::B, wall);
+                    self.connect(cell_key, direction);
+                }
+            }
+            Some(Action::CellMove(cell_key, position)) => {
+                self.entities.cells[cell_key]
+                    .pool
+                    .apply(&self.config.move_cost);
+                self.move_cell(cell_key, position);
+            }
+            Some(Action::GeneMove(gene_key, cell_key, target_cell_key)) => {
+                self.entities.move_gene(gene_key, cell_key, target_cell_key);
+                // processors on the gene travel along with it
+                for processor in self.processors.values_mut() {
+                    if processor.cell_key() == cell_key && processor.gene_key() == gene_key {
+                        processor.move_to_cell(target_cell_key);
+                    }
+                }
+            }
+            Some(Action::GeneCopy(gene_key, target_cell_key, id)) => {
+                let code = self.entities.genes[gene_key].code().to_vec();
+                let cell_key = self.processors[processor_key].cell_key();
+                self.entities.cells[cell_key]
+                    .pool
+                    .consume(Molecule::I, code.len() as i64);
+                self.entities.create_gene_in_cell_with_id(
+                    id,
+                    target_cell_key,
+                    &code,
+                    &self.config.instruction_lookup,
+                );
+            }
+            Some(Action::Input(cell_key, org_id)) => {
+                if let Some(value) = self.entities.cells[cell_key].organelle_read(org_id, rng) {
+                    self.processors[processor_key].stack.push(value);
+                }
+            }
+            Some(Action::Output(cell_key, org_id, value)) => {
+                let cell = &mut self.entities.cells[cell_key];
+                match &cell.organelles[org_id] {
+                    Organelle::Connection(connection) => {
+                        let direction = connection.direction;
+                        self.entities.send(cell_key, direction, value);
+                    }
+                    _ => {
+                        let light = self
+                            .entities
+                            .grid
+                            .position(cell_key)
+                            .map_or(0.0, |position| self.light_at(position));
+                        self.entities.cells[cell_key].organelle_write(org_id, value, light, rng);
+                    }
+                }
+            }
+            Some(Action::Ingest(cell_key, molecule, amount)) => {
+                if let Some(position) = self.entities.grid.position(cell_key) {
+                    self.entities
+                        .grid
+                        .environment_mut(position)
+                        .consume(molecule, amount);
+                    self.entities.cells[cell_key].pool.add(molecule, amount);
+                }
+            }
+            Some(Action::Expel(cell_key, molecule, amount)) => {
+                if let Some(position) = self.entities.grid.position(cell_key) {
+                    self.entities.cells[cell_key].pool.consume(molecule, amount);
+                    self.entities
+                        .grid
+                        .environment_mut(position)
+                        .add(molecule, amount);
+                }
+            }
+            Some(Action::MoleculeMove(cell_key, target_cell_key, molecule, amount)) => {
+                self.entities.cells[cell_key].pool.consume(molecule, amount);
+                self.entities.cells[target_cell_key]
+                    .pool
+                    .add(molecule, amount);
+            }
+            Some(Action::Emit(cell_key, channel, amount)) => {
+                if let Some(position) = self.entities.grid.position(cell_key) {
+                    self.entities.scents.emit(channel, position, amount as f64);
+                }
+            }
+            Some(Action::Excite(gene_key)) => {
+                let gene = &mut self.entities.genes[gene_key];
+                gene.activation = (gene.activation + 1).min(self.config.max_activation);
+            }
+            Some(Action::Inhibit(gene_key)) => {
+                let gene = &mut self.entities.genes[gene_key];
+                gene.activation = gene.activation.saturating_sub(1);
+            }
+            Some(Action::Send(cell_key, value)) => {
+                self.entities.cells[cell_key].messages.push_back(value);
+            }
+            Some(Action::Receive(cell_key)) => {
+                if let Some(value) = self.entities.cells[cell_key].messages.pop_front() {
+                    self.processors[processor_key].stack.push(value);
+                }
+            }
+            Some(Action::RaiseBarrier(cell_key, amount)) => {
+                let cell = &mut self.entities.cells[cell_key];
+                cell.pool.consume(Molecule::B, amount);
+                cell.set_barrier(cell.barrier() + amount);
+            }
+            Some(Action::LowerBarrier(cell_key, amount)) => {
+                let cell = &mut self.entities.cells[cell_key];
+                cell.set_barrier(cell.barrier() - amount);
+                cell.pool.add(Molecule::B, amount);
+            }
+            Some(Action::Attack(cell_key, target_cell_key)) => {
+                self.entities.cells[cell_key]
+                    .pool
+                    .consume(Molecule::A, self.config.attack_cost);
+                // the broken down barrier is lost
+                let target = &mut self.entities.cells[target_cell_key];
+                target.set_barrier((target.barrier() - self.config.attack_strength).max(0));
+            }
+            Some(Action::SetOpen(cell_key, org_id, gate, open)) => {
+                self.entities.cells[cell_key].organelles[org_id].set_open(gate, open);
             }
             None => {}
         }
     }
 
+    pub fn gene_processors_amount(&self, gene_key: GeneKey) -> usize {
+        self.processors
+            .values()
+            .filter(|processor| processor.start_gene_key() == gene_key)
+            .count()
+    }
+
     pub fn execute<R: Rng>(&mut self, rng: &mut R) {
-        for (_, processor) in self.processors.iter_mut() {
-            World::execute_processor(processor, &mut self.entities, &self.config, rng);
+        // processors created during this tick only start on the next one
+        let processor_keys: Vec<ProcessorKey> = self.processors.keys().collect();
+        for processor_key in processor_keys {
+            // processors on more activated genes execute more instructions
+            let activation = match self.processors.get(processor_key) {
+                Some(processor) => self.entities.genes[processor.gene_key()].activation,
+                None => continue,
+            };
+            for _ in 0..activation {
+                self.execute_processor(processor_key, rng);
+            }
+        }
+        self.decay_walls();
+        self.remove_dead_cells();
+        self.diffuse();
+        self.entities
+            .scents
+            .update(&self.entities.grid, &self.config.scent_channels);
+        self.tick += 1;
+    }
+
+    // the amount of ticks executed so far
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn signature(&self, cell_key: CellKey) -> [u32; 3] {
+        self.entities.signature(cell_key)
+    }
+
+    pub fn observe(&self, cell_key: CellKey, direction: Direction) -> [u32; 3] {
+        self.entities.observe(cell_key, direction)
+    }
+
+    pub fn scent_amount(&self, channel: usize, position: Position) -> f64 {
+        self.entities.scents.amount(channel, position)
+    }
+
+    pub fn light_at(&self, position: Position) -> f64 {
+        self.config
+            .light
+            .at(position, self.entities.grid.width, self.tick)
+    }
+
+    pub fn diffuse(&mut self) {
+        for (molecule, &rate) in self.config.diffusion_rates.iter() {
+            if rate > 0.0 {
+                self.entities.grid.diffuse(molecule, rate);
+            }
+        }
+    }
+
+    pub fn decay_walls(&mut self) {
+        let wall_decay = self.config.wall_decay;
+        for cell in self.entities.cells.values_mut() {
+            let wall = cell.pool.amount(Molecule::B);
+            cell.pool.set_amount(Molecule::B, wall - wall_decay);
+        }
+    }
+
+    // a cell dies if its wall is down to 0
+    pub fn remove_dead_cells(&mut self) {
+        let dead_cell_keys: Vec<CellKey> = self
+            .entities
+            .cells
+            .iter()
+            .filter(|(_, cell)| cell.pool.amount(Molecule::B) <= 0)
+            .map(|(cell_key, _)| cell_key)
+            .collect();
+        for cell_key in dead_cell_keys {
+            self.kill_cell(cell_key);
         }
     }
 
@@ -85,6 +475,101 @@ impl World {
 }
 
 impl Entities {
+    // The ids of the three most active genes in a cell, most active first.
+    // Genes that are equally active are ordered by id. If there are fewer
+    // genes the rest is 0.
+    pub fn signature(&self, cell_key: CellKey) -> [u32; 3] {
+        let mut genes: Vec<&Gene> = self.cells[cell_key]
+            .gene_keys()
+            .map(|gene_key| &self.genes[gene_key])
+            .collect();
+        genes.sort_by_key(|gene| (std::cmp::Reverse(gene.activation), gene.id));
+        let mut signature = [0; 3];
+        for (id, gene) in signature.iter_mut().zip(genes) {
+            *id = gene.id;
+        }
+        signature
+    }
+
+    // the signature of the neighbor in direction, or 3 x 0 if there is none
+    pub fn observe(&self, cell_key: CellKey, direction: Direction) -> [u32; 3] {
+        self.grid
+            .neighbor(cell_key, direction)
+            .map_or([0; 3], |neighbor_key| self.signature(neighbor_key))
+    }
+
+    // The neighbor in direction and its connection organelle facing us,
+    // if both sides are connected to each other.
+    pub fn connection_peer(
+        &self,
+        cell_key: CellKey,
+        direction: Direction,
+    ) -> Option<(CellKey, usize)> {
+        let (_, connection) = self.cells[cell_key].connection(direction)?;
+        let peer_key = connection.peer?;
+        // the peer may have died or moved away
+        if self.grid.neighbor(cell_key, direction) != Some(peer_key) {
+            return None;
+        }
+        let (peer_org_id, peer_connection) =
+            self.cells[peer_key].connection(direction.opposite())?;
+        if peer_connection.peer != Some(cell_key) {
+            return None;
+        }
+        Some((peer_key, peer_org_id))
+    }
+
+    // the connected neighbor in direction, if its side is open for gate
+    // and its barrier is down
+    pub fn connection_target(
+        &self,
+        cell_key: CellKey,
+        direction: Direction,
+        gate: Gate,
+    ) -> Option<CellKey> {
+        let (peer_key, peer_org_id) = self.connection_peer(cell_key, direction)?;
+        let peer = &self.cells[peer_key];
+        if peer.organelles[peer_org_id].is_open(gate) != Some(true) || peer.is_barrier_closed() {
+            return None;
+        }
+        Some(peer_key)
+    }
+
+    // output to a connection arrives as input on the other side
+    pub fn send(&mut self, cell_key: CellKey, direction: Direction, value: u32) -> bool {
+        match self.connection_peer(cell_key, direction) {
+            Some((peer_key, _)) => {
+                self.cells[peer_key]
+                    .connection_mut(direction.opposite())
+                    .unwrap()
+                    .input
+                    .push_back(value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn create_cell_at(&mut self, position: Position) -> Option<CellKey> {
+        if !self.grid.is_free(position) {
+            return None;
+        }
+        let cell_key = self.cells.insert(Cell::new());
+        self.grid.insert(cell_key, position);
+        Some(cell_key)
+    }
+
+    pub fn remove_cell(&mut self, cell_key: CellKey) {
+        self.grid.remove(cell_key);
+        if let Some(cell) = self.cells.remove(cell_key) {
+            for gene_key in cell.gene_keys() {
+                if let Some(gene) = self.genes.remove(gene_key) {
+                    self.gene_by_id.remove(&gene.id);
+                }
+            }
+        }
+    }
+
     pub fn create_gene_id<R: Rng>(&self, rng: &mut R) -> u32 {
         loop {
             let id: u32 = rng.gen();
@@ -109,14 +594,21 @@ impl Entities {
         }
     }
 
+    pub fn move_gene(&mut self, gene_key: GeneKey, cell_key: CellKey, target_cell_key: CellKey) {
+        let coordinates = self.genes[gene_key].coordinates();
+        self.cells[cell_key].remove_gene(gene_key);
+        self.cells[target_cell_key].add_gene(gene_key, coordinates);
+    }
+
     pub fn create_gene_in_cell<R: Rng>(
         &mut self,
         cell_key: CellKey,
         code: &[u32],
+        instruction_lookup: &Lookup<Instruction>,
         rng: &mut R,
     ) -> GeneKey {
         let id = self.create_gene_id(rng);
-        self.create_gene_in_cell_with_id(id, cell_key, code)
+        self.create_gene_in_cell_with_id(id, cell_key, code, instruction_lookup)
     }
 
     pub fn create_gene_in_cell_with_id(
@@ -124,8 +616,9 @@ impl Entities {
         id: u32,
         cell_key: CellKey,
         code: &[u32],
+        instruction_lookup: &Lookup<Instruction>,
     ) -> GeneKey {
-        let gene = Gene::new(id, code);
+        let gene = Gene::new(id, code, instruction_lookup);
         let coordinates = gene.coordinates();
         let gene_key = self.genes.insert(gene);
         self.gene_by_id.insert(id, gene_key);
@@ -1,11 +1,24 @@
+use enum_map::enum_map;
 use rand::SeedableRng;
-
-use caldo::lookup;
-use caldo::processor::{Config, Instruction, ProcessorInstruction};
+use std::collections::HashMap;
+
+use caldo::grid::{Direction, Topology};
+use caldo::light::Light;
+use caldo::lookup::{self, Perturbation};
+use caldo::organelle::{Environment, Gate, Organelle};
+use caldo::port::Catalyst;
+use caldo::processor::{Config, Instruction, ProcessorInstruction, ProcessorKey};
+use caldo::reaction::{self, Molecule, Reaction};
+use caldo::scent::ScentChannel;
 use caldo::stack;
+use caldo::task::Fixed;
 use caldo::world::World;
 
 const INSTR_BIT: u32 = 0x01000000;
+const CALL_MODE: u32 = 0x02000000;
+const NOOP_MODE: u32 = 0x03000000;
+const TRUE: u32 = 0xFFFFFFFF;
+const FALSE: u32 = 0;
 const ADD_NR: u32 = stack::Instruction::Add as u32 | INSTR_BIT;
 const SUB_NR: u32 = stack::Instruction::Sub as u32 | INSTR_BIT;
 const DUP_NR: u32 = stack::Instruction::Dup as u32 | INSTR_BIT;
@@ -16,10 +29,45 @@ const LOOKUP_NR: u32 = ProcessorInstruction::Lookup as u32 | INSTR_BIT;
 const GENE_READ_NR: u32 = ProcessorInstruction::GeneRead as u32 | INSTR_BIT;
 const GENE_WRITE_NR: u32 = ProcessorInstruction::GeneWrite as u32 | INSTR_BIT;
 const GENE_CREATE_NR: u32 = ProcessorInstruction::GeneCreate as u32 | INSTR_BIT;
+const PROC_CREATE_NR: u32 = ProcessorInstruction::ProcCreate as u32 | INSTR_BIT;
+const PROC_DESTROY_NR: u32 = ProcessorInstruction::ProcDestroy as u32 | INSTR_BIT;
+const PROC_END_NR: u32 = ProcessorInstruction::ProcEnd as u32 | INSTR_BIT;
+const CELL_NR: u32 = ProcessorInstruction::Cell as u32 | INSTR_BIT;
+const MOVE_GENE_NR: u32 = ProcessorInstruction::MoveGene as u32 | INSTR_BIT;
+const COPY_GENE_NR: u32 = ProcessorInstruction::CopyGene as u32 | INSTR_BIT;
+const MOVE_NR: u32 = ProcessorInstruction::Move as u32 | INSTR_BIT;
+const LOOKUP_ORG_NR: u32 = ProcessorInstruction::LookupOrg as u32 | INSTR_BIT;
+const INPUT_NR: u32 = ProcessorInstruction::Input as u32 | INSTR_BIT;
+const HAS_INPUT_NR: u32 = ProcessorInstruction::HasInput as u32 | INSTR_BIT;
+const OUTPUT_NR: u32 = ProcessorInstruction::Output as u32 | INSTR_BIT;
+const OPEN_GENE_NR: u32 = ProcessorInstruction::OpenGene as u32 | INSTR_BIT;
+const OPEN_CHEM_NR: u32 = ProcessorInstruction::OpenChem as u32 | INSTR_BIT;
+const CLOSE_GENE_NR: u32 = ProcessorInstruction::CloseGene as u32 | INSTR_BIT;
+const CLOSE_CHEM_NR: u32 = ProcessorInstruction::CloseChem as u32 | INSTR_BIT;
+const IS_OPEN_GENE_NR: u32 = ProcessorInstruction::IsOpenGene as u32 | INSTR_BIT;
+const IS_OPEN_CHEM_NR: u32 = ProcessorInstruction::IsOpenChem as u32 | INSTR_BIT;
+const INGEST_NR: u32 = ProcessorInstruction::Ingest as u32 | INSTR_BIT;
+const EXPEL_NR: u32 = ProcessorInstruction::Expel as u32 | INSTR_BIT;
+const EMIT_NR: u32 = ProcessorInstruction::Emit as u32 | INSTR_BIT;
+const SMELL_NR: u32 = ProcessorInstruction::Smell as u32 | INSTR_BIT;
+const OBSERVE_NR: u32 = ProcessorInstruction::Observe as u32 | INSTR_BIT;
+const EXCITE_NR: u32 = ProcessorInstruction::Excite as u32 | INSTR_BIT;
+const INHIBIT_NR: u32 = ProcessorInstruction::Inhibit as u32 | INSTR_BIT;
+const ACTIVATION_NR: u32 = ProcessorInstruction::Activation as u32 | INSTR_BIT;
+const SEND_NR: u32 = ProcessorInstruction::Send as u32 | INSTR_BIT;
+const RECEIVE_NR: u32 = ProcessorInstruction::Receive as u32 | INSTR_BIT;
+const RAISE_BARRIER_NR: u32 = ProcessorInstruction::RaiseBarrier as u32 | INSTR_BIT;
+const LOWER_BARRIER_NR: u32 = ProcessorInstruction::LowerBarrier as u32 | INSTR_BIT;
+const ATTACK_NR: u32 = ProcessorInstruction::Attack as u32 | INSTR_BIT;
+const CHEM_AMOUNT_NR: u32 = ProcessorInstruction::ChemAmount as u32 | INSTR_BIT;
+const WALL_AMOUNT_NR: u32 = ProcessorInstruction::WallAmount as u32 | INSTR_BIT;
+const ENV_AMOUNT_NR: u32 = ProcessorInstruction::EnvAmount as u32 | INSTR_BIT;
+const SENSE_CELL_NR: u32 = ProcessorInstruction::SenseCell as u32 | INSTR_BIT;
+const SENSE_CHEM_NR: u32 = ProcessorInstruction::SenseChem as u32 | INSTR_BIT;
 
 const SEED: [u8; 16] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
 
-fn instruction_lookup<'a>() -> lookup::Lookup<Instruction> {
+fn instruction_lookup() -> lookup::Lookup<Instruction> {
     let mut l = lookup::Lookup::<Instruction>::new();
     let mut add = |instruction: Instruction| {
         l.add(instruction.coordinates(), instruction)
@@ -47,7 +95,112 @@ fn instruction_lookup<'a>() -> lookup::Lookup<Instruction> {
     add(Instruction::ProcessorInstruction(
         ProcessorInstruction::GeneCreate,
     ));
-    return l;
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::ProcCreate,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::ProcDestroy,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::ProcEnd,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::Cell,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::MoveGene,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::CopyGene,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::Move,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::LookupOrg,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::Input,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::HasInput,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::Output,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::OpenGene,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::OpenChem,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::CloseGene,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::CloseChem,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::IsOpenGene,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::IsOpenChem,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::Ingest,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::Expel,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::Emit,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::Smell,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::Observe,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::Excite,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::Inhibit,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::Activation,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::Send,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::Receive,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::RaiseBarrier,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::LowerBarrier,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::Attack,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::ChemAmount,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::WallAmount,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::EnvAmount,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::SenseCell,
+    ));
+    add(Instruction::ProcessorInstruction(
+        ProcessorInstruction::SenseChem,
+    ));
+    l
 }
 
 #[test]
@@ -56,9 +209,10 @@ fn test_processor_execute() {
         instruction_lookup: instruction_lookup(),
         max_stack_size: 1000,
         max_call_stack_size: 1000,
+        ..Default::default()
     };
     let mut world = World::new(config);
-    let cell_key = world.create_cell();
+    let cell_key = world.create_cell().unwrap();
     let gene_key = world.create_gene(&[3, 4, ADD_NR]);
     let processor_key = world.create_processor(cell_key, gene_key);
     let mut rng = rand_pcg::Pcg32::from_seed(SEED);
@@ -76,9 +230,10 @@ fn test_processor_execute_multiple() {
         instruction_lookup: instruction_lookup(),
         max_stack_size: 1000,
         max_call_stack_size: 1000,
+        ..Default::default()
     };
     let mut world = World::new(config);
-    let cell_key = world.create_cell();
+    let cell_key = world.create_cell().unwrap();
     let gene_key = world.create_gene(&[3, 4, ADD_NR, 6, SUB_NR]);
     let processor_key = world.create_processor(cell_key, gene_key);
     let mut rng = rand_pcg::Pcg32::from_seed(SEED);
@@ -96,9 +251,10 @@ fn test_processor_execute_beyond_end() {
         instruction_lookup: instruction_lookup(),
         max_stack_size: 1000,
         max_call_stack_size: 1000,
+        ..Default::default()
     };
     let mut world = World::new(config);
-    let cell_key = world.create_cell();
+    let cell_key = world.create_cell().unwrap();
     let gene_key = world.create_gene(&[3, 4, ADD_NR]);
     let processor_key = world.create_processor(cell_key, gene_key);
     let mut rng = rand_pcg::Pcg32::from_seed(SEED);
@@ -122,9 +278,10 @@ fn test_processor_execute_nearby() {
         instruction_lookup: instruction_lookup(),
         max_stack_size: 1000,
         max_call_stack_size: 1000,
+        ..Default::default()
     };
     let mut world = World::new(config);
-    let cell_key = world.create_cell();
+    let cell_key = world.create_cell().unwrap();
     let gene_key = world.create_gene(&[3, 4, ADD_NR + 1, 6, SUB_NR - 1]);
     let processor_key = world.create_processor(cell_key, gene_key);
     let mut rng = rand_pcg::Pcg32::from_seed(SEED);
@@ -142,9 +299,10 @@ fn test_processor_execute_stack_underflow() {
         instruction_lookup: instruction_lookup(),
         max_stack_size: 1000,
         max_call_stack_size: 1000,
+        ..Default::default()
     };
     let mut world = World::new(config);
-    let cell_key = world.create_cell();
+    let cell_key = world.create_cell().unwrap();
     let gene_key = world.create_gene(&[4, ADD_NR]);
     let processor_key = world.create_processor(cell_key, gene_key);
     let mut rng = rand_pcg::Pcg32::from_seed(SEED);
@@ -162,9 +320,10 @@ fn test_processor_execute_stack_overflow_numbers() {
         instruction_lookup: instruction_lookup(),
         max_stack_size: 4,
         max_call_stack_size: 1000,
+        ..Default::default()
     };
     let mut world = World::new(config);
-    let cell_key = world.create_cell();
+    let cell_key = world.create_cell().unwrap();
     let gene_key = world.create_gene(&[1, 2, 3, 4, 5]);
     let processor_key = world.create_processor(cell_key, gene_key);
     let mut rng = rand_pcg::Pcg32::from_seed(SEED);
@@ -187,9 +346,10 @@ fn test_processor_execute_stack_overflow_instructions() {
         instruction_lookup: instruction_lookup(),
         max_stack_size: 4,
         max_call_stack_size: 1000,
+        ..Default::default()
     };
     let mut world = World::new(config);
-    let cell_key = world.create_cell();
+    let cell_key = world.create_cell().unwrap();
     let gene_key = world.create_gene(&[1, DUP_NR, DUP_NR, DUP_NR, DUP_NR]);
     let processor_key = world.create_processor(cell_key, gene_key);
     let mut rng = rand_pcg::Pcg32::from_seed(SEED);
@@ -212,9 +372,10 @@ fn test_jf() {
         instruction_lookup: instruction_lookup(),
         max_stack_size: 1000,
         max_call_stack_size: 1000,
+        ..Default::default()
     };
     let mut world = World::new(config);
-    let cell_key = world.create_cell();
+    let cell_key = world.create_cell().unwrap();
     let gene_key = world.create_gene(&[1, 1, JF_NR, 66, 77]);
     let processor_key = world.create_processor(cell_key, gene_key);
     let mut rng = rand_pcg::Pcg32::from_seed(SEED);
@@ -232,9 +393,10 @@ fn test_jf2() {
         instruction_lookup: instruction_lookup(),
         max_stack_size: 1000,
         max_call_stack_size: 1000,
+        ..Default::default()
     };
     let mut world = World::new(config);
-    let cell_key = world.create_cell();
+    let cell_key = world.create_cell().unwrap();
     let gene_key = world.create_gene(&[1, 2, JF_NR, 66, 77, 88]);
     let processor_key = world.create_processor(cell_key, gene_key);
     let mut rng = rand_pcg::Pcg32::from_seed(SEED);
@@ -251,9 +413,10 @@ fn test_jf_too_far() {
         instruction_lookup: instruction_lookup(),
         max_stack_size: 1000,
         max_call_stack_size: 1000,
+        ..Default::default()
     };
     let mut world = World::new(config);
-    let cell_key = world.create_cell();
+    let cell_key = world.create_cell().unwrap();
     let gene_key = world.create_gene(&[1, 200, JF_NR, 66, 88]);
     let processor_key = world.create_processor(cell_key, gene_key);
     let mut rng = rand_pcg::Pcg32::from_seed(SEED);
@@ -271,9 +434,10 @@ fn test_jf_false() {
         instruction_lookup: instruction_lookup(),
         max_stack_size: 1000,
         max_call_stack_size: 1000,
+        ..Default::default()
     };
     let mut world = World::new(config);
-    let cell_key = world.create_cell();
+    let cell_key = world.create_cell().unwrap();
     let gene_key = world.create_gene(&[0, 1, JF_NR, 66, 88]);
     let processor_key = world.create_processor(cell_key, gene_key);
     let mut rng = rand_pcg::Pcg32::from_seed(SEED);
@@ -290,9 +454,10 @@ fn test_jf_zero() {
         instruction_lookup: instruction_lookup(),
         max_stack_size: 1000,
         max_call_stack_size: 1000,
+        ..Default::default()
     };
     let mut world = World::new(config);
-    let cell_key = world.create_cell();
+    let cell_key = world.create_cell().unwrap();
     let gene_key = world.create_gene(&[1, 0, JF_NR, 66, 88]);
     let processor_key = world.create_processor(cell_key, gene_key);
     let mut rng = rand_pcg::Pcg32::from_seed(SEED);
@@ -309,9 +474,10 @@ fn test_jb() {
         instruction_lookup: instruction_lookup(),
         max_stack_size: 1000,
         max_call_stack_size: 1000,
+        ..Default::default()
     };
     let mut world = World::new(config);
-    let cell_key = world.create_cell();
+    let cell_key = world.create_cell().unwrap();
     let gene_key = world.create_gene(&[88, 1, 3, JB_NR, 66]);
     let processor_key = world.create_processor(cell_key, gene_key);
     let mut rng = rand_pcg::Pcg32::from_seed(SEED);
@@ -329,9 +495,10 @@ fn test_jb_false() {
         instruction_lookup: instruction_lookup(),
         max_stack_size: 1000,
         max_call_stack_size: 1000,
+        ..Default::default()
     };
     let mut world = World::new(config);
-    let cell_key = world.create_cell();
+    let cell_key = world.create_cell().unwrap();
     let gene_key = world.create_gene(&[88, 0, 3, JB_NR, 66]);
     let processor_key = world.create_processor(cell_key, gene_key);
     let mut rng = rand_pcg::Pcg32::from_seed(SEED);
@@ -349,9 +516,10 @@ fn test_jb_1() {
         instruction_lookup: instruction_lookup(),
         max_stack_size: 1000,
         max_call_stack_size: 1000,
+        ..Default::default()
     };
     let mut world = World::new(config);
-    let cell_key = world.create_cell();
+    let cell_key = world.create_cell().unwrap();
     let gene_key = world.create_gene(&[88, 1, 1, JB_NR, 66]);
     let processor_key = world.create_processor(cell_key, gene_key);
     let mut rng = rand_pcg::Pcg32::from_seed(SEED);
@@ -368,9 +536,10 @@ fn test_jb_zero() {
         instruction_lookup: instruction_lookup(),
         max_stack_size: 1000,
         max_call_stack_size: 1000,
+        ..Default::default()
     };
     let mut world = World::new(config);
-    let cell_key = world.create_cell();
+    let cell_key = world.create_cell().unwrap();
     let gene_key = world.create_gene(&[88, 1, 0, JB_NR, 66]);
     let processor_key = world.create_processor(cell_key, gene_key);
     let mut rng = rand_pcg::Pcg32::from_seed(SEED);
@@ -387,9 +556,10 @@ fn test_jb_too_far() {
         instruction_lookup: instruction_lookup(),
         max_stack_size: 1000,
         max_call_stack_size: 1000,
+        ..Default::default()
     };
     let mut world = World::new(config);
-    let cell_key = world.create_cell();
+    let cell_key = world.create_cell().unwrap();
     let gene_key = world.create_gene(&[88, 1, 100, JB_NR, 66]);
     let processor_key = world.create_processor(cell_key, gene_key);
     let mut rng = rand_pcg::Pcg32::from_seed(SEED);
@@ -407,9 +577,10 @@ fn test_lookup() {
         instruction_lookup: instruction_lookup(),
         max_stack_size: 1000,
         max_call_stack_size: 1000,
+        ..Default::default()
     };
     let mut world = World::new(config);
-    let cell_key = world.create_cell();
+    let cell_key = world.create_cell().unwrap();
     let mut rng = rand_pcg::Pcg32::from_seed(SEED);
     let gene1_key = world.create_gene_in_cell(cell_key, &[3, 4, ADD_NR], &mut rng);
     let gene1_id = world.entities.genes[gene1_key].id;
@@ -428,10 +599,11 @@ fn test_lookup_in_other_cell_fails() {
         instruction_lookup: instruction_lookup(),
         max_stack_size: 1000,
         max_call_stack_size: 1000,
+        ..Default::default()
     };
     let mut world = World::new(config);
-    let cell1_key = world.create_cell();
-    let cell2_key = world.create_cell();
+    let cell1_key = world.create_cell().unwrap();
+    let cell2_key = world.create_cell().unwrap();
     let mut rng = rand_pcg::Pcg32::from_seed(SEED);
     // put this gene in another cell, so lookup should find itself
     world.create_gene_in_cell(cell2_key, &[3, 4, ADD_NR], &mut rng);
@@ -452,9 +624,10 @@ fn test_call_without_return() {
         instruction_lookup: instruction_lookup(),
         max_stack_size: 1000,
         max_call_stack_size: 1000,
+        ..Default::default()
     };
     let mut world = World::new(config);
-    let cell_key = world.create_cell();
+    let cell_key = world.create_cell().unwrap();
     let mut rng = rand_pcg::Pcg32::from_seed(SEED);
     world.create_gene_in_cell(cell_key, &[3, 4, ADD_NR], &mut rng);
     // 5 3
@@ -477,11 +650,12 @@ fn test_call_impossible_gene_id() {
         instruction_lookup: instruction_lookup(),
         max_stack_size: 1000,
         max_call_stack_size: 1000,
+        ..Default::default()
     };
     let mut world = World::new(config);
-    let cell_key = world.create_cell();
+    let cell_key = world.create_cell().unwrap();
     let mut rng = rand_pcg::Pcg32::from_seed(SEED);
-    let other_cell_key = world.create_cell();
+    let other_cell_key = world.create_cell().unwrap();
     let other_cell_gene_key = world.create_gene_in_cell(other_cell_key, &[6, 7, 8], &mut rng);
     let other_cell_gene_id = world.entities.genes[other_cell_gene_key].id;
     let gene_key = world.create_gene_in_cell(
@@ -504,9 +678,10 @@ fn test_call_gene_id_in_another_cell() {
         instruction_lookup: instruction_lookup(),
         max_stack_size: 1000,
         max_call_stack_size: 1000,
+        ..Default::default()
     };
     let mut world = World::new(config);
-    let cell_key = world.create_cell();
+    let cell_key = world.create_cell().unwrap();
     let mut rng = rand_pcg::Pcg32::from_seed(SEED);
     let gene_key = world.create_gene_in_cell(cell_key, &[5, CALL_NR, 1, 6, ADD_NR], &mut rng);
     let processor_key = world.create_processor(cell_key, gene_key);
@@ -524,9 +699,10 @@ fn test_call_and_return() {
         instruction_lookup: instruction_lookup(),
         max_stack_size: 1000,
         max_call_stack_size: 1000,
+        ..Default::default()
     };
     let mut world = World::new(config);
-    let cell_key = world.create_cell();
+    let cell_key = world.create_cell().unwrap();
     let mut rng = rand_pcg::Pcg32::from_seed(SEED);
     world.create_gene_in_cell(cell_key, &[3, 4, ADD_NR], &mut rng);
 
@@ -554,9 +730,10 @@ fn test_call_at_end() {
         instruction_lookup: instruction_lookup(),
         max_stack_size: 1000,
         max_call_stack_size: 1000,
+        ..Default::default()
     };
     let mut world = World::new(config);
-    let cell_key = world.create_cell();
+    let cell_key = world.create_cell().unwrap();
     let mut rng = rand_pcg::Pcg32::from_seed(SEED);
 
     world.create_gene_in_cell(cell_key, &[3, 4, ADD_NR], &mut rng);
@@ -579,15 +756,123 @@ fn test_call_at_end() {
     assert_eq!(p.failures, 0);
 }
 
+#[test]
+fn test_number_mode() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell_key = world.create_cell().unwrap();
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let gene_key = world.create_gene_in_cell(cell_key, &[0x00FFFFFF, 3], &mut rng);
+    let processor_key = world.create_processor(cell_key, gene_key);
+
+    world.execute_amount(2, &mut rng);
+
+    let p = &world.processors[processor_key];
+    assert_eq!(p.stack, [0x00FFFFFF, 3]);
+    assert_eq!(p.failures, 0);
+}
+
+#[test]
+fn test_instruction_mode() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell_key = world.create_cell().unwrap();
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let gene_key = world.create_gene_in_cell(cell_key, &[3, 4, ADD_NR], &mut rng);
+    let processor_key = world.create_processor(cell_key, gene_key);
+
+    world.execute_amount(3, &mut rng);
+
+    let p = &world.processors[processor_key];
+    assert_eq!(p.stack, [7]);
+    assert_eq!(p.failures, 0);
+}
+
+#[test]
+fn test_call_mode() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell_key = world.create_cell().unwrap();
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    world.create_gene_in_cell(cell_key, &[3, 4, ADD_NR], &mut rng);
+    // call the gene at coordinates 3 directly, then return
+    // 5
+    // 5 3
+    // 5 3 4
+    // 5 7
+    // 5 7 4
+    let gene2_key = world.create_gene_in_cell(cell_key, &[5, CALL_MODE | 3, 4], &mut rng);
+    let processor_key = world.create_processor(cell_key, gene2_key);
+
+    world.execute_amount(6, &mut rng);
+
+    let p = &world.processors[processor_key];
+    assert_eq!(p.stack, [5, 7, 4]);
+    assert_eq!(p.failures, 0);
+}
+
+#[test]
+fn test_call_mode_without_genes() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell_key = world.create_cell().unwrap();
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    // the gene is not in the cell, so there is nothing to call
+    let gene_key = world.create_gene(&[5, CALL_MODE | 3, 4]);
+    let processor_key = world.create_processor(cell_key, gene_key);
+
+    world.execute_amount(3, &mut rng);
+
+    let p = &world.processors[processor_key];
+    assert_eq!(p.stack, [5, 4]);
+    assert_eq!(p.failures, 1);
+}
+
+#[test]
+fn test_noop_mode() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell_key = world.create_cell().unwrap();
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let gene_key = world.create_gene_in_cell(
+        cell_key,
+        &[3, NOOP_MODE | 4, 0xFF000005, 4, ADD_NR],
+        &mut rng,
+    );
+    let processor_key = world.create_processor(cell_key, gene_key);
+
+    world.execute_amount(5, &mut rng);
+
+    let p = &world.processors[processor_key];
+    assert_eq!(p.stack, [7]);
+    assert_eq!(p.failures, 0);
+}
+
 #[test]
 fn test_call_stack_compaction() {
     let config = Config {
         instruction_lookup: instruction_lookup(),
         max_stack_size: 1000,
         max_call_stack_size: 2,
+        ..Default::default()
     };
     let mut world = World::new(config);
-    let cell_key = world.create_cell();
+    let cell_key = world.create_cell().unwrap();
     let mut rng = rand_pcg::Pcg32::from_seed(SEED);
 
     world.create_gene_in_cell(cell_key, &[1, 2, LOOKUP_NR, CALL_NR], &mut rng);
@@ -610,9 +895,10 @@ fn test_read_gene() {
         instruction_lookup: instruction_lookup(),
         max_stack_size: 1000,
         max_call_stack_size: 1000,
+        ..Default::default()
     };
     let mut world = World::new(config);
-    let cell_key = world.create_cell();
+    let cell_key = world.create_cell().unwrap();
     let mut rng = rand_pcg::Pcg32::from_seed(SEED);
     world.create_gene_in_cell(cell_key, &[3, 4, ADD_NR], &mut rng);
     let gene_key =
@@ -631,9 +917,10 @@ fn test_read_gene_other_index() {
         instruction_lookup: instruction_lookup(),
         max_stack_size: 1000,
         max_call_stack_size: 1000,
+        ..Default::default()
     };
     let mut world = World::new(config);
-    let cell_key = world.create_cell();
+    let cell_key = world.create_cell().unwrap();
     let mut rng = rand_pcg::Pcg32::from_seed(SEED);
     world.create_gene_in_cell(cell_key, &[3, 4, ADD_NR], &mut rng);
     let gene_key =
@@ -652,9 +939,10 @@ fn test_read_gene_beyond_end() {
         instruction_lookup: instruction_lookup(),
         max_stack_size: 1000,
         max_call_stack_size: 1000,
+        ..Default::default()
     };
     let mut world = World::new(config);
-    let cell_key = world.create_cell();
+    let cell_key = world.create_cell().unwrap();
     let mut rng = rand_pcg::Pcg32::from_seed(SEED);
     world.create_gene_in_cell(cell_key, &[3, 4, ADD_NR], &mut rng);
     let gene_key =
@@ -673,9 +961,11 @@ fn test_write_gene() {
         instruction_lookup: instruction_lookup(),
         max_stack_size: 1000,
         max_call_stack_size: 1000,
+        ..Default::default()
     };
     let mut world = World::new(config);
-    let cell_key = world.create_cell();
+    let cell_key = world.create_cell().unwrap();
+    world.set_molecule_amount(cell_key, Molecule::I, 10);
     let mut rng = rand_pcg::Pcg32::from_seed(SEED);
     let gene1_key = world.create_gene_in_cell(cell_key, &[3, 4, ADD_NR], &mut rng);
     let gene2_key =
@@ -684,7 +974,68 @@ fn test_write_gene() {
 
     world.execute_amount(5, &mut rng);
 
-    assert_eq!(world.entities.genes[gene1_key].code, [3, 4, ADD_NR, 10]);
+    assert_eq!(world.entities.genes[gene1_key].code(), [3, 4, ADD_NR, 10]);
+    // the written instruction is made of I
+    assert_eq!(world.molecule_amount(cell_key, Molecule::I), 9);
+}
+
+#[test]
+fn test_write_gene_without_material() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell_key = world.create_cell().unwrap();
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let gene1_key = world.create_gene_in_cell(cell_key, &[3, 4, ADD_NR], &mut rng);
+    let gene2_key =
+        world.create_gene_in_cell(cell_key, &[5, 3, LOOKUP_NR, 10, GENE_WRITE_NR], &mut rng);
+    let processor_key = world.create_processor(cell_key, gene2_key);
+
+    world.execute_amount(5, &mut rng);
+
+    // there is no I to write the instruction with
+    assert_eq!(world.processors[processor_key].failures, 1);
+    assert_eq!(world.entities.genes[gene1_key].code(), [3, 4, ADD_NR]);
+}
+
+#[test]
+fn test_write_gene_is_executed() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell_key = world.create_cell().unwrap();
+    world.set_molecule_amount(cell_key, Molecule::I, 10);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let gene1_key = world.create_gene_in_cell(cell_key, &[3, 4, ADD_NR], &mut rng);
+    // read the ADD instruction from the gene and write it again
+    let gene2_key = world.create_gene_in_cell(
+        cell_key,
+        &[5, 3, LOOKUP_NR, DUP_NR, 2, GENE_READ_NR, GENE_WRITE_NR],
+        &mut rng,
+    );
+    let processor2_key = world.create_processor(cell_key, gene2_key);
+
+    world.execute_amount(7, &mut rng);
+
+    assert_eq!(world.processors[processor2_key].stack, [5]);
+    assert_eq!(
+        world.entities.genes[gene1_key].code(),
+        [3, 4, ADD_NR, ADD_NR]
+    );
+
+    world.processors.remove(processor2_key);
+    let processor1_key = world.create_processor(cell_key, gene1_key);
+
+    world.execute_amount(4, &mut rng);
+
+    // the written instruction is executed, and fails on a short stack
+    let p = &world.processors[processor1_key];
+    assert_eq!(p.stack, []);
+    assert_eq!(p.failures, 1);
 }
 
 #[test]
@@ -693,9 +1044,11 @@ fn test_create_gene() {
         instruction_lookup: instruction_lookup(),
         max_stack_size: 1000,
         max_call_stack_size: 1000,
+        ..Default::default()
     };
     let mut world = World::new(config);
-    let cell_key = world.create_cell();
+    let cell_key = world.create_cell().unwrap();
+    world.set_molecule_amount(cell_key, Molecule::I, 10);
     let mut rng = rand_pcg::Pcg32::from_seed(SEED);
     let gene_key = world.create_gene_in_cell(
         cell_key,
@@ -708,3 +1061,2182 @@ fn test_create_gene() {
 
     // assert_eq!(world.entities.genes[gene1_key].code, [3, 4, ADD_NR, 10]);
 }
+
+#[test]
+fn test_proc_create() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        max_stack_size: 1000,
+        max_call_stack_size: 1000,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell_key = world.create_cell().unwrap();
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let gene1_key = world.create_gene_in_cell(cell_key, &[3, 4, ADD_NR], &mut rng);
+    let gene2_key =
+        world.create_gene_in_cell(cell_key, &[5, 3, LOOKUP_NR, 1, PROC_CREATE_NR], &mut rng);
+    world.create_processor(cell_key, gene2_key);
+
+    world.execute_amount(5, &mut rng);
+
+    assert_eq!(world.processors.len(), 2);
+    assert_eq!(world.gene_processors_amount(gene1_key), 1);
+
+    // the new processor starts at index 1
+    world.execute_amount(1, &mut rng);
+
+    let p = world
+        .processors
+        .values()
+        .find(|p| p.gene_key() == gene1_key)
+        .unwrap();
+    assert_eq!(p.stack, [4]);
+    assert_eq!(p.failures, 0);
+}
+
+#[test]
+fn test_proc_create_beyond_end() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        max_stack_size: 1000,
+        max_call_stack_size: 1000,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell_key = world.create_cell().unwrap();
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    world.create_gene_in_cell(cell_key, &[3, 4, ADD_NR], &mut rng);
+    let gene2_key =
+        world.create_gene_in_cell(cell_key, &[5, 3, LOOKUP_NR, 100, PROC_CREATE_NR], &mut rng);
+    let processor_key = world.create_processor(cell_key, gene2_key);
+
+    world.execute_amount(5, &mut rng);
+
+    assert_eq!(world.processors.len(), 1);
+    let p = &world.processors[processor_key];
+    assert_eq!(p.stack, [5]);
+    assert_eq!(p.failures, 1);
+}
+
+#[test]
+fn test_proc_create_limit() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        max_stack_size: 1000,
+        max_call_stack_size: 1000,
+        max_processors_per_gene: 1,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell_key = world.create_cell().unwrap();
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let gene1_key = world.create_gene_in_cell(cell_key, &[3, 4, ADD_NR], &mut rng);
+    let gene2_key =
+        world.create_gene_in_cell(cell_key, &[5, 3, LOOKUP_NR, 0, PROC_CREATE_NR], &mut rng);
+    let processor_key = world.create_processor(cell_key, gene2_key);
+
+    world.execute_amount(10, &mut rng);
+
+    assert_eq!(world.processors.len(), 2);
+    assert_eq!(world.gene_processors_amount(gene1_key), 1);
+    let p = &world.processors[processor_key];
+    assert_eq!(p.failures, 1);
+}
+
+#[test]
+fn test_proc_destroy() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        max_stack_size: 1000,
+        max_call_stack_size: 1000,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell_key = world.create_cell().unwrap();
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let gene1_key = world.create_gene_in_cell(cell_key, &[3, 4, ADD_NR], &mut rng);
+    let gene2_key = world.create_gene_in_cell(cell_key, &[5, PROC_DESTROY_NR], &mut rng);
+    let processor1_key = world.create_processor(cell_key, gene1_key);
+    let processor2_key = world.create_processor(cell_key, gene2_key);
+
+    world.execute_amount(2, &mut rng);
+
+    assert_eq!(world.processors.len(), 1);
+    assert!(world.processors.contains_key(processor1_key));
+    assert!(!world.processors.contains_key(processor2_key));
+}
+
+#[test]
+fn test_proc_end() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        max_stack_size: 1000,
+        max_call_stack_size: 1000,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell_key = world.create_cell().unwrap();
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let gene1_key = world.create_gene_in_cell(cell_key, &[3, 4, ADD_NR], &mut rng);
+    let gene2_key = world.create_gene_in_cell(cell_key, &[5, 3, LOOKUP_NR, PROC_END_NR], &mut rng);
+    world.create_processor(cell_key, gene1_key);
+    world.create_processor(cell_key, gene1_key);
+    let processor_key = world.create_processor(cell_key, gene2_key);
+
+    world.execute_amount(4, &mut rng);
+
+    assert_eq!(world.processors.len(), 1);
+    assert_eq!(world.gene_processors_amount(gene1_key), 0);
+    let p = &world.processors[processor_key];
+    assert_eq!(p.stack, [5]);
+    assert_eq!(p.failures, 0);
+}
+
+#[test]
+fn test_proc_end_spares_callers() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        max_stack_size: 1000,
+        max_call_stack_size: 1000,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell_key = world.create_cell().unwrap();
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let gene1_key = world.create_gene_in_cell(cell_key, &[3, 4, ADD_NR], &mut rng);
+    let gene2_key = world.create_gene_in_cell(cell_key, &[5, 3, LOOKUP_NR, CALL_NR], &mut rng);
+    let gene3_key = world.create_gene_in_cell(cell_key, &[7, 3, LOOKUP_NR, PROC_END_NR], &mut rng);
+    let caller_key = world.create_processor(cell_key, gene2_key);
+    world.create_processor(cell_key, gene3_key);
+
+    world.execute_amount(4, &mut rng);
+
+    // the caller is running gene 1, but it was started on gene 2
+    assert_eq!(world.processors[caller_key].gene_key(), gene1_key);
+    assert_eq!(world.gene_processors_amount(gene1_key), 0);
+    assert_eq!(world.gene_processors_amount(gene2_key), 1);
+    assert_eq!(world.processors.len(), 2);
+}
+
+#[test]
+fn test_create_cell_at() {
+    let config = Config {
+        width: 3,
+        height: 3,
+        topology: Topology::Bounded,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell1_key = world.create_cell_at((1, 1)).unwrap();
+    let cell2_key = world.create_cell_at((1, 0)).unwrap();
+
+    assert_eq!(world.create_cell_at((1, 1)), None);
+    assert_eq!(world.create_cell_at((3, 1)), None);
+    assert_eq!(world.cell_position(cell1_key), Some((1, 1)));
+    assert_eq!(world.cell_at((1, 0)), Some(cell2_key));
+    assert_eq!(world.neighbor(cell1_key, Direction::North), Some(cell2_key));
+    assert_eq!(world.neighbor(cell2_key, Direction::South), Some(cell1_key));
+    assert_eq!(world.neighbor(cell1_key, Direction::East), None);
+    assert_eq!(world.neighbor(cell2_key, Direction::North), None);
+}
+
+#[test]
+fn test_create_cell_toroidal_neighbor() {
+    let config = Config {
+        width: 3,
+        height: 3,
+        topology: Topology::Toroidal,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell1_key = world.create_cell_at((0, 0)).unwrap();
+    let cell2_key = world.create_cell_at((2, 0)).unwrap();
+
+    assert_eq!(world.neighbor(cell1_key, Direction::West), Some(cell2_key));
+    assert_eq!(world.neighbor(cell2_key, Direction::East), Some(cell1_key));
+}
+
+#[test]
+fn test_create_cell_grid_full() {
+    let config = Config {
+        width: 2,
+        height: 1,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell1_key = world.create_cell().unwrap();
+    let cell2_key = world.create_cell().unwrap();
+
+    assert_ne!(cell1_key, cell2_key);
+    assert_eq!(world.create_cell(), None);
+    assert_eq!(world.entities.cells.len(), 2);
+}
+
+#[test]
+fn test_remove_cell() {
+    let config = Config {
+        width: 3,
+        height: 3,
+        topology: Topology::Bounded,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell1_key = world.create_cell_at((1, 1)).unwrap();
+    let cell2_key = world.create_cell_at((1, 0)).unwrap();
+    let gene_key = world.create_gene(&[3, 4, ADD_NR]);
+    world.create_processor(cell2_key, gene_key);
+
+    world.remove_cell(cell2_key);
+
+    assert!(!world.entities.cells.contains_key(cell2_key));
+    assert_eq!(world.cell_at((1, 0)), None);
+    assert_eq!(world.cell_position(cell2_key), None);
+    assert_eq!(world.neighbor(cell1_key, Direction::North), None);
+    assert_eq!(world.processors.len(), 0);
+    // the location can be reused
+    assert!(world.create_cell_at((1, 0)).is_some());
+}
+
+#[test]
+fn test_cell_create() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        width: 3,
+        height: 3,
+        topology: Topology::Bounded,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell_key = world.create_cell_at((1, 1)).unwrap();
+    world.set_molecule_amount(cell_key, Molecule::B, 300);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let gene_key = world.create_gene_in_cell(cell_key, &[1, CELL_NR], &mut rng);
+    let processor_key = world.create_processor(cell_key, gene_key);
+
+    world.execute_amount(2, &mut rng);
+
+    let p = &world.processors[processor_key];
+    assert_eq!(p.stack, [TRUE]);
+    assert_eq!(p.failures, 0);
+    assert_eq!(world.entities.cells.len(), 2);
+    let new_cell_key = world.neighbor(cell_key, Direction::East).unwrap();
+    assert_eq!(world.cell_position(new_cell_key), Some((2, 1)));
+    // the wall of the new cell comes from its parent
+    assert_eq!(world.molecule_amount(cell_key, Molecule::B), 200);
+    assert_eq!(world.molecule_amount(new_cell_key, Molecule::B), 100);
+    // the new cell starts connected
+    assert_eq!(
+        world.entities.connection_peer(cell_key, Direction::East),
+        Some((new_cell_key, 0))
+    );
+    assert_eq!(
+        world
+            .entities
+            .connection_peer(new_cell_key, Direction::West),
+        Some((cell_key, 0))
+    );
+}
+
+#[test]
+fn test_cell_create_occupied() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        width: 3,
+        height: 3,
+        topology: Topology::Bounded,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell_key = world.create_cell_at((1, 1)).unwrap();
+    world.create_cell_at((1, 2)).unwrap();
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let gene_key = world.create_gene_in_cell(cell_key, &[2, CELL_NR], &mut rng);
+    let processor_key = world.create_processor(cell_key, gene_key);
+
+    world.execute_amount(2, &mut rng);
+
+    let p = &world.processors[processor_key];
+    assert_eq!(p.stack, [FALSE]);
+    assert_eq!(p.failures, 0);
+    assert_eq!(world.entities.cells.len(), 2);
+}
+
+#[test]
+fn test_cell_create_beyond_edge() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        width: 3,
+        height: 3,
+        topology: Topology::Bounded,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell_key = world.create_cell_at((0, 0)).unwrap();
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let gene_key = world.create_gene_in_cell(cell_key, &[0, CELL_NR], &mut rng);
+    let processor_key = world.create_processor(cell_key, gene_key);
+
+    world.execute_amount(2, &mut rng);
+
+    let p = &world.processors[processor_key];
+    assert_eq!(p.stack, [FALSE]);
+    assert_eq!(world.entities.cells.len(), 1);
+}
+
+#[test]
+fn test_cell_create_cost() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        width: 3,
+        height: 3,
+        topology: Topology::Bounded,
+        cell_create_cost: Reaction::new(enum_map! {
+            Molecule::A => -10,
+            Molecule::B => -5,
+            _ => 0
+        }),
+        initial_wall: 1,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell_key = world.create_cell_at((1, 1)).unwrap();
+    world.set_molecule_amount(cell_key, Molecule::A, 15);
+    world.set_molecule_amount(cell_key, Molecule::B, 10);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let gene_key = world.create_gene_in_cell(cell_key, &[1, CELL_NR, 3, CELL_NR], &mut rng);
+    let processor_key = world.create_processor(cell_key, gene_key);
+
+    world.execute_amount(4, &mut rng);
+
+    // the second cell cannot be paid for
+    let p = &world.processors[processor_key];
+    assert_eq!(p.stack, [TRUE, FALSE]);
+    assert_eq!(world.entities.cells.len(), 2);
+    assert_eq!(world.molecule_amount(cell_key, Molecule::A), 5);
+    assert_eq!(world.molecule_amount(cell_key, Molecule::B), 10 - 5 - 1);
+}
+
+#[test]
+fn test_cell_create_not_enough_wall() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        width: 3,
+        height: 3,
+        topology: Topology::Bounded,
+        initial_wall: 100,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell_key = world.create_cell_at((1, 1)).unwrap();
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let gene_key = world.create_gene_in_cell(cell_key, &[1, CELL_NR], &mut rng);
+    let processor_key = world.create_processor(cell_key, gene_key);
+
+    world.execute_amount(2, &mut rng);
+
+    // giving away its whole wall would kill the parent
+    let p = &world.processors[processor_key];
+    assert_eq!(p.stack, [FALSE]);
+    assert_eq!(world.entities.cells.len(), 1);
+    assert_eq!(world.molecule_amount(cell_key, Molecule::B), 100);
+}
+
+#[test]
+fn test_lookup_empty_cell() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell_key = world.create_cell().unwrap();
+    let gene_key = world.create_gene(&[5, LOOKUP_NR]);
+    let processor_key = world.create_processor(cell_key, gene_key);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+
+    world.execute_amount(2, &mut rng);
+
+    let p = &world.processors[processor_key];
+    assert_eq!(p.stack, []);
+    assert_eq!(p.failures, 1);
+}
+
+#[test]
+fn test_move_gene() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        width: 3,
+        height: 3,
+        topology: Topology::Bounded,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell1_key = world.create_cell_at((1, 1)).unwrap();
+    let cell2_key = world.create_cell_at((2, 1)).unwrap();
+    world.connect(cell1_key, Direction::East);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let gene1_key = world.create_gene_in_cell(cell1_key, &[3, 4, ADD_NR], &mut rng);
+    let gene1_id = world.entities.genes[gene1_key].id;
+    let gene2_key =
+        world.create_gene_in_cell(cell1_key, &[5, 3, LOOKUP_NR, 1, MOVE_GENE_NR], &mut rng);
+    let processor1_key = world.create_processor(cell1_key, gene1_key);
+    let processor2_key = world.create_processor(cell1_key, gene2_key);
+
+    world.execute_amount(5, &mut rng);
+
+    assert_eq!(world.processors[processor2_key].failures, 0);
+    assert!(!world.entities.cells[cell1_key].has_gene(gene1_key));
+    assert!(world.entities.cells[cell2_key].has_gene(gene1_key));
+    assert_eq!(
+        world.entities.get_gene_key(cell2_key, gene1_id),
+        Some(gene1_key)
+    );
+    assert_eq!(world.entities.get_gene_key(cell1_key, gene1_id), None);
+    // the processor on the gene moved along with it
+    assert_eq!(world.processors[processor1_key].cell_key(), cell2_key);
+}
+
+#[test]
+fn test_move_gene_barrier_closed() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        width: 3,
+        height: 3,
+        topology: Topology::Bounded,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell1_key = world.create_cell_at((1, 1)).unwrap();
+    let cell2_key = world.create_cell_at((2, 1)).unwrap();
+    world.connect(cell1_key, Direction::East);
+    world.entities.cells[cell2_key].set_barrier(10);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let gene1_key = world.create_gene_in_cell(cell1_key, &[3, 4, ADD_NR], &mut rng);
+    let gene2_key =
+        world.create_gene_in_cell(cell1_key, &[5, 3, LOOKUP_NR, 1, MOVE_GENE_NR], &mut rng);
+    let processor_key = world.create_processor(cell1_key, gene2_key);
+
+    world.execute_amount(5, &mut rng);
+
+    assert_eq!(world.processors[processor_key].failures, 1);
+    assert!(world.entities.cells[cell1_key].has_gene(gene1_key));
+    assert!(!world.entities.cells[cell2_key].has_gene(gene1_key));
+}
+
+#[test]
+fn test_move_gene_no_neighbor() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        width: 3,
+        height: 3,
+        topology: Topology::Bounded,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell_key = world.create_cell_at((1, 1)).unwrap();
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let gene1_key = world.create_gene_in_cell(cell_key, &[3, 4, ADD_NR], &mut rng);
+    let gene2_key =
+        world.create_gene_in_cell(cell_key, &[5, 3, LOOKUP_NR, 1, MOVE_GENE_NR], &mut rng);
+    let processor_key = world.create_processor(cell_key, gene2_key);
+
+    world.execute_amount(5, &mut rng);
+
+    assert_eq!(world.processors[processor_key].failures, 1);
+    assert!(world.entities.cells[cell_key].has_gene(gene1_key));
+}
+
+#[test]
+fn test_move_gene_return_to_moved_gene() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        width: 3,
+        height: 3,
+        topology: Topology::Bounded,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell1_key = world.create_cell_at((1, 1)).unwrap();
+    let cell2_key = world.create_cell_at((2, 1)).unwrap();
+    world.connect(cell1_key, Direction::East);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let gene1_key = world.create_gene_in_cell(cell1_key, &[3, 4, ADD_NR], &mut rng);
+    // calls gene 1
+    let gene2_key = world.create_gene_in_cell(cell1_key, &[7, 3, LOOKUP_NR, CALL_NR], &mut rng);
+    // moves gene 2 while gene 1 is being called
+    let gene3_key =
+        world.create_gene_in_cell(cell1_key, &[9, 7, LOOKUP_NR, 1, MOVE_GENE_NR], &mut rng);
+    let processor_key = world.create_processor(cell1_key, gene2_key);
+    world.create_processor(cell1_key, gene3_key);
+
+    world.execute_amount(7, &mut rng);
+
+    assert!(world.entities.cells[cell2_key].has_gene(gene2_key));
+    // gene 2 cannot be returned to, so we start gene 1 again
+    let p = &world.processors[processor_key];
+    assert_eq!(p.gene_key(), gene1_key);
+    assert_eq!(p.cell_key(), cell1_key);
+    assert_eq!(p.stack, [7, 7]);
+    assert_eq!(p.call_stack.len(), 0);
+    assert_eq!(p.failures, 0);
+}
+
+#[test]
+fn test_copy_gene() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        width: 3,
+        height: 3,
+        topology: Topology::Bounded,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell1_key = world.create_cell_at((1, 1)).unwrap();
+    let cell2_key = world.create_cell_at((1, 2)).unwrap();
+    world.connect(cell1_key, Direction::South);
+    world.set_molecule_amount(cell1_key, Molecule::I, 10);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let gene1_key = world.create_gene_in_cell(cell1_key, &[3, 4, ADD_NR], &mut rng);
+    let gene2_key =
+        world.create_gene_in_cell(cell1_key, &[5, 3, LOOKUP_NR, 2, COPY_GENE_NR], &mut rng);
+    let processor_key = world.create_processor(cell1_key, gene2_key);
+
+    world.execute_amount(5, &mut rng);
+
+    assert_eq!(world.processors[processor_key].failures, 0);
+    assert!(world.entities.cells[cell1_key].has_gene(gene1_key));
+    let copy_id = world.entities.cells[cell2_key]
+        .lookup_gene_id(&world.entities.genes, 3)
+        .unwrap();
+    assert_ne!(copy_id, world.entities.genes[gene1_key].id);
+    let copy_key = world.entities.get_gene_key(cell2_key, copy_id).unwrap();
+    assert_eq!(world.entities.genes[copy_key].code(), [3, 4, ADD_NR]);
+    // the copy is made of I, one per instruction
+    assert_eq!(world.molecule_amount(cell1_key, Molecule::I), 7);
+}
+
+#[test]
+fn test_copy_gene_barrier_closed() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        width: 3,
+        height: 3,
+        topology: Topology::Bounded,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell1_key = world.create_cell_at((1, 1)).unwrap();
+    let cell2_key = world.create_cell_at((1, 2)).unwrap();
+    world.connect(cell1_key, Direction::South);
+    world.entities.cells[cell2_key].set_barrier(1);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    world.create_gene_in_cell(cell1_key, &[3, 4, ADD_NR], &mut rng);
+    let gene2_key =
+        world.create_gene_in_cell(cell1_key, &[5, 3, LOOKUP_NR, 2, COPY_GENE_NR], &mut rng);
+    let processor_key = world.create_processor(cell1_key, gene2_key);
+
+    world.execute_amount(5, &mut rng);
+
+    assert_eq!(world.processors[processor_key].failures, 1);
+    assert_eq!(
+        world.entities.cells[cell2_key].lookup_gene_id(&world.entities.genes, 3),
+        None
+    );
+}
+
+#[test]
+fn test_copy_gene_without_material() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        width: 3,
+        height: 3,
+        topology: Topology::Bounded,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell1_key = world.create_cell_at((1, 1)).unwrap();
+    let cell2_key = world.create_cell_at((1, 2)).unwrap();
+    world.connect(cell1_key, Direction::South);
+    // one short of the 3 instructions of the gene
+    world.set_molecule_amount(cell1_key, Molecule::I, 2);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    world.create_gene_in_cell(cell1_key, &[3, 4, ADD_NR], &mut rng);
+    let gene2_key =
+        world.create_gene_in_cell(cell1_key, &[5, 3, LOOKUP_NR, 2, COPY_GENE_NR], &mut rng);
+    let processor_key = world.create_processor(cell1_key, gene2_key);
+
+    world.execute_amount(5, &mut rng);
+
+    assert_eq!(world.processors[processor_key].failures, 1);
+    assert_eq!(world.molecule_amount(cell1_key, Molecule::I), 2);
+    assert_eq!(
+        world.entities.cells[cell2_key].lookup_gene_id(&world.entities.genes, 3),
+        None
+    );
+}
+
+// all I in the world: in pools, in the environment and built into genes
+fn total_instruction_material(world: &World) -> i64 {
+    let in_cells: i64 = world
+        .entities
+        .cells
+        .values()
+        .map(|cell| {
+            let in_genes: usize = cell
+                .gene_keys()
+                .map(|gene_key| world.entities.genes[gene_key].code().len())
+                .sum();
+            cell.pool.amount(Molecule::I) + in_genes as i64
+        })
+        .sum();
+    let in_environment: i64 = (0..3)
+        .flat_map(|x| (0..3).map(move |y| (x, y)))
+        .map(|position| world.environment_amount(position, Molecule::I))
+        .sum();
+    in_cells + in_environment
+}
+
+#[test]
+fn test_copy_gene_conserves_material() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        width: 3,
+        height: 3,
+        topology: Topology::Bounded,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell1_key = world.create_cell_at((1, 1)).unwrap();
+    let cell2_key = world.create_cell_at((1, 2)).unwrap();
+    world.connect(cell1_key, Direction::South);
+    world.set_molecule_amount(cell1_key, Molecule::I, 10);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    world.create_gene_in_cell(cell1_key, &[3, 4, ADD_NR], &mut rng);
+    let gene2_key =
+        world.create_gene_in_cell(cell1_key, &[5, 3, LOOKUP_NR, 2, COPY_GENE_NR], &mut rng);
+    world.create_processor(cell1_key, gene2_key);
+    let before = total_instruction_material(&world);
+
+    world.execute_amount(5, &mut rng);
+    world.kill_cell(cell2_key);
+
+    assert_eq!(world.environment_amount((1, 2), Molecule::I), 3);
+    assert_eq!(total_instruction_material(&world), before);
+}
+
+#[test]
+fn test_molecule_amount() {
+    let mut world = World::new(Config::default());
+    let cell1_key = world.create_cell().unwrap();
+    let cell2_key = world.create_cell().unwrap();
+
+    world.set_molecule_amount(cell1_key, Molecule::G, 100);
+
+    assert_eq!(world.molecule_amount(cell1_key, Molecule::G), 100);
+    assert_eq!(world.molecule_amount(cell1_key, Molecule::A), 0);
+    assert_eq!(world.molecule_amount(cell2_key, Molecule::G), 0);
+}
+
+#[test]
+fn test_move() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        width: 3,
+        height: 3,
+        topology: Topology::Bounded,
+        move_cost: Reaction::new(enum_map! {
+            Molecule::G => -2,
+            _ => 0
+        }),
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell_key = world.create_cell_at((1, 1)).unwrap();
+    world.create_cell_at((1, 2)).unwrap();
+    world.set_molecule_amount(cell_key, Molecule::G, 5);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    // east, then off the edge, then back west, then north
+    let gene_key = world.create_gene_in_cell(
+        cell_key,
+        &[1, MOVE_NR, 1, MOVE_NR, 3, MOVE_NR, 0, MOVE_NR],
+        &mut rng,
+    );
+    let processor_key = world.create_processor(cell_key, gene_key);
+
+    world.execute_amount(6, &mut rng);
+
+    let p = &world.processors[processor_key];
+    assert_eq!(p.stack, [TRUE, FALSE, TRUE]);
+    assert_eq!(world.cell_position(cell_key), Some((1, 1)));
+    assert_eq!(world.cell_at((2, 1)), None);
+    assert_eq!(world.molecule_amount(cell_key, Molecule::G), 1);
+
+    // the last move cannot be paid for
+    world.execute_amount(2, &mut rng);
+
+    let p = &world.processors[processor_key];
+    assert_eq!(p.stack, [TRUE, FALSE, TRUE, FALSE]);
+    assert_eq!(world.cell_position(cell_key), Some((1, 1)));
+}
+
+#[test]
+fn test_move_into_cell() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        width: 3,
+        height: 3,
+        topology: Topology::Bounded,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell_key = world.create_cell_at((1, 1)).unwrap();
+    world.create_cell_at((1, 2)).unwrap();
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let gene_key = world.create_gene_in_cell(cell_key, &[2, MOVE_NR], &mut rng);
+    let processor_key = world.create_processor(cell_key, gene_key);
+
+    world.execute_amount(2, &mut rng);
+
+    assert_eq!(world.processors[processor_key].stack, [FALSE]);
+    assert_eq!(world.cell_position(cell_key), Some((1, 1)));
+}
+
+#[test]
+fn test_instruction_cost() {
+    let mut instruction_costs = HashMap::new();
+    instruction_costs.insert(Instruction::StackInstruction(stack::Instruction::Add), 5);
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        number_cost: 1,
+        instruction_cost: 2,
+        instruction_costs,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell_key = world.create_cell().unwrap();
+    world.set_molecule_amount(cell_key, Molecule::A, 100);
+    let gene_key = world.create_gene(&[3, 4, ADD_NR, DUP_NR]);
+    let processor_key = world.create_processor(cell_key, gene_key);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+
+    world.execute_amount(4, &mut rng);
+
+    let p = &world.processors[processor_key];
+    assert_eq!(p.stack, [7, 7]);
+    assert_eq!(
+        world.molecule_amount(cell_key, Molecule::A),
+        100 - 1 - 1 - 5 - 2
+    );
+}
+
+#[test]
+fn test_instruction_cost_stall() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        number_cost: 1,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell_key = world.create_cell().unwrap();
+    world.set_molecule_amount(cell_key, Molecule::A, 2);
+    let gene_key = world.create_gene(&[1, 2, 3, 4]);
+    let processor_key = world.create_processor(cell_key, gene_key);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+
+    world.execute_amount(4, &mut rng);
+
+    let p = &world.processors[processor_key];
+    assert_eq!(p.stack, [1, 2]);
+    assert_eq!(p.failures, 0);
+    assert_eq!(world.molecule_amount(cell_key, Molecule::A), 0);
+
+    // once there is ATP again, the processor continues where it stalled
+    world.set_molecule_amount(cell_key, Molecule::A, 1);
+    world.execute_amount(2, &mut rng);
+
+    let p = &world.processors[processor_key];
+    assert_eq!(p.stack, [1, 2, 3]);
+}
+
+#[test]
+fn test_stack_cost() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        number_cost: 1,
+        stack_cost_divisor: 1,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell_key = world.create_cell().unwrap();
+    // 1 + 0, 1 + 1, 1 + 4, 1 + 9
+    world.set_molecule_amount(cell_key, Molecule::A, 1 + 2 + 5);
+    let gene_key = world.create_gene(&[1, 2, 3, 4]);
+    let processor_key = world.create_processor(cell_key, gene_key);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+
+    world.execute_amount(4, &mut rng);
+
+    let p = &world.processors[processor_key];
+    assert_eq!(p.stack, [1, 2, 3]);
+    assert_eq!(world.molecule_amount(cell_key, Molecule::A), 0);
+}
+
+#[test]
+fn test_stack_cost_capped() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        number_cost: 1,
+        stack_cost_divisor: 1,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell_key = world.create_cell().unwrap();
+    world.set_molecule_amount(cell_key, Molecule::A, 1000);
+    let gene_key = world.create_gene(&[1]);
+    let processor_key = world.create_processor(cell_key, gene_key);
+    // 1 + 40 * 40 is more ATP than a cell can hold
+    world.processors[processor_key].stack = vec![0; 40];
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+
+    world.execute_amount(1, &mut rng);
+
+    let p = &world.processors[processor_key];
+    assert_eq!(p.stack.len(), 41);
+    assert_eq!(world.molecule_amount(cell_key, Molecule::A), 0);
+}
+
+#[test]
+fn test_wall_decay() {
+    let config = Config {
+        initial_wall: 25,
+        wall_decay: 10,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell_key = world.create_cell().unwrap();
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+
+    world.execute_amount(2, &mut rng);
+
+    assert_eq!(world.molecule_amount(cell_key, Molecule::B), 5);
+
+    world.execute_amount(1, &mut rng);
+
+    assert!(!world.entities.cells.contains_key(cell_key));
+}
+
+#[test]
+fn test_cell_death() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        width: 3,
+        height: 3,
+        topology: Topology::Bounded,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell_key = world.create_cell_at((1, 1)).unwrap();
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let gene1_key = world.create_gene_in_cell(cell_key, &[3, 4, ADD_NR], &mut rng);
+    let gene1_id = world.entities.genes[gene1_key].id;
+    let gene2_key = world.create_gene_in_cell(cell_key, &[5, 6], &mut rng);
+    world.create_processor(cell_key, gene1_key);
+    world.create_processor(cell_key, gene2_key);
+    world.set_molecule_amount(cell_key, Molecule::G, 10);
+    world.set_molecule_amount(cell_key, Molecule::A, 20);
+    world.set_environment_amount((1, 1), Molecule::G, 1);
+
+    world.execute_amount(1, &mut rng);
+
+    assert!(world.entities.cells.contains_key(cell_key));
+
+    world.set_molecule_amount(cell_key, Molecule::B, 0);
+    world.execute_amount(1, &mut rng);
+
+    assert!(!world.entities.cells.contains_key(cell_key));
+    assert_eq!(world.cell_at((1, 1)), None);
+    assert_eq!(world.processors.len(), 0);
+    assert!(!world.entities.genes.contains_key(gene1_key));
+    assert!(!world.entities.genes.contains_key(gene2_key));
+    assert_eq!(world.entities.get_gene_key(cell_key, gene1_id), None);
+    // the materials end up in the environment
+    assert_eq!(world.environment_amount((1, 1), Molecule::G), 11);
+    assert_eq!(world.environment_amount((1, 1), Molecule::I), 5);
+    // ATP dissipates
+    assert_eq!(world.environment_amount((1, 1), Molecule::A), 0);
+    assert_eq!(world.environment_amount((1, 2), Molecule::G), 0);
+}
+
+#[test]
+fn test_catalyst() {
+    let mut world = World::new(Config::default());
+    let cell_key = world.create_cell().unwrap();
+    world.set_molecule_amount(cell_key, Molecule::G, 2);
+    world.set_molecule_amount(cell_key, Molecule::O, 12);
+    let catalyst = Catalyst::new(
+        reaction::respiration(),
+        Box::new(Fixed::new(&[3, 4], &[7, 12])),
+    );
+    let index = world.add_organelle(cell_key, 0x010000, Organelle::Catalyst(catalyst));
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+
+    let cell = &mut world.entities.cells[cell_key];
+    assert_eq!(cell.organelle_read(index, &mut rng), Some(3));
+    assert_eq!(cell.organelle_read(index, &mut rng), Some(4));
+    assert_eq!(cell.organelle_write(index, 7, 1.0, &mut rng), Some(0));
+    assert_eq!(cell.organelle_write(index, 12, 1.0, &mut rng), Some(2));
+    assert_eq!(cell.organelle_write(index + 1, 12, 1.0, &mut rng), None);
+
+    assert_eq!(world.molecule_amount(cell_key, Molecule::G), 0);
+    assert_eq!(world.molecule_amount(cell_key, Molecule::A), 76);
+}
+
+fn respiration_catalyst(input: &[u32], expected_output: &[u32]) -> Organelle {
+    Organelle::Catalyst(Catalyst::new(
+        reaction::respiration(),
+        Box::new(Fixed::new(input, expected_output)),
+    ))
+}
+
+#[test]
+fn test_lookup_org() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell_key = world.create_cell().unwrap();
+    world.add_organelle(cell_key, 0x010000, respiration_catalyst(&[1], &[1]));
+    world.add_organelle(cell_key, 0x020000, respiration_catalyst(&[2], &[2]));
+    let gene_key = world.create_gene_in_cell(
+        cell_key,
+        &[0x020001, LOOKUP_ORG_NR, 0x010001, LOOKUP_ORG_NR],
+        &mut rng,
+    );
+    let processor_key = world.create_processor(cell_key, gene_key);
+
+    world.execute_amount(4, &mut rng);
+
+    let processor = &world.processors[processor_key];
+    assert_eq!(processor.stack, [1, 0]);
+    assert_eq!(processor.failures, 0);
+}
+
+#[test]
+fn test_lookup_org_no_organelles() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell_key = world.create_cell().unwrap();
+    let gene_key = world.create_gene_in_cell(cell_key, &[0x010000, LOOKUP_ORG_NR], &mut rng);
+    let processor_key = world.create_processor(cell_key, gene_key);
+
+    world.execute_amount(2, &mut rng);
+
+    let processor = &world.processors[processor_key];
+    assert_eq!(processor.stack, []);
+    assert_eq!(processor.failures, 1);
+}
+
+#[test]
+fn test_input_output() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell_key = world.create_cell().unwrap();
+    world.set_molecule_amount(cell_key, Molecule::G, 1);
+    world.set_molecule_amount(cell_key, Molecule::O, 6);
+    world.add_organelle(cell_key, 0x010000, respiration_catalyst(&[3, 4], &[7]));
+    let gene_key = world.create_gene_in_cell(
+        cell_key,
+        &[0, 0, INPUT_NR, 0, INPUT_NR, ADD_NR, OUTPUT_NR],
+        &mut rng,
+    );
+    let processor_key = world.create_processor(cell_key, gene_key);
+
+    world.execute_amount(6, &mut rng);
+    assert_eq!(world.processors[processor_key].stack, [0, 7]);
+
+    world.execute_amount(1, &mut rng);
+    let processor = &world.processors[processor_key];
+    assert_eq!(processor.stack, []);
+    assert_eq!(processor.failures, 0);
+    assert_eq!(world.molecule_amount(cell_key, Molecule::G), 0);
+    assert_eq!(world.molecule_amount(cell_key, Molecule::A), 38);
+}
+
+#[test]
+fn test_input_unknown_organelle() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell_key = world.create_cell().unwrap();
+    world.add_organelle(cell_key, 0x010000, respiration_catalyst(&[3, 4], &[7]));
+    let gene_key = world.create_gene_in_cell(cell_key, &[1, INPUT_NR, 1, 2, OUTPUT_NR], &mut rng);
+    let processor_key = world.create_processor(cell_key, gene_key);
+
+    world.execute_amount(5, &mut rng);
+
+    let processor = &world.processors[processor_key];
+    assert_eq!(processor.stack, []);
+    assert_eq!(processor.failures, 2);
+}
+
+#[test]
+fn test_has_input() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell_key = world.create_cell().unwrap();
+    world.add_organelle(cell_key, 0x010000, respiration_catalyst(&[3], &[3]));
+    let gene_key = world.create_gene_in_cell(
+        cell_key,
+        &[0, HAS_INPUT_NR, 0, INPUT_NR, 0, HAS_INPUT_NR],
+        &mut rng,
+    );
+    let processor_key = world.create_processor(cell_key, gene_key);
+
+    world.execute_amount(6, &mut rng);
+
+    let processor = &world.processors[processor_key];
+    assert_eq!(processor.stack, [TRUE, 3, FALSE]);
+    assert_eq!(processor.failures, 0);
+}
+
+fn connected_world() -> World {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        width: 3,
+        height: 3,
+        topology: Topology::Bounded,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell1_key = world.create_cell_at((1, 1)).unwrap();
+    world.create_cell_at((2, 1)).unwrap();
+    world.connect(cell1_key, Direction::East);
+    world
+}
+
+#[test]
+fn test_connect() {
+    let mut world = connected_world();
+    let cell1_key = world.cell_at((1, 1)).unwrap();
+    let cell2_key = world.cell_at((2, 1)).unwrap();
+    assert_eq!(
+        world.entities.connection_peer(cell1_key, Direction::East),
+        Some((cell2_key, 0))
+    );
+    assert_eq!(
+        world.entities.connection_peer(cell2_key, Direction::West),
+        Some((cell1_key, 0))
+    );
+    assert_eq!(
+        world.entities.connection_peer(cell1_key, Direction::West),
+        None
+    );
+    // nothing to connect to
+    assert!(!world.connect(cell1_key, Direction::West));
+}
+
+#[test]
+fn test_connection_broken_by_death() {
+    let mut world = connected_world();
+    let cell1_key = world.cell_at((1, 1)).unwrap();
+    let cell2_key = world.cell_at((2, 1)).unwrap();
+    world.remove_cell(cell2_key);
+    let cell3_key = world.create_cell_at((2, 1)).unwrap();
+    assert_eq!(
+        world.entities.connection_peer(cell1_key, Direction::East),
+        None
+    );
+    world.connect(cell3_key, Direction::West);
+    assert_eq!(
+        world.entities.connection_peer(cell1_key, Direction::East),
+        Some((cell3_key, 0))
+    );
+}
+
+#[test]
+fn test_connection_output_input() {
+    let mut world = connected_world();
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell1_key = world.cell_at((1, 1)).unwrap();
+    let cell2_key = world.cell_at((2, 1)).unwrap();
+    let gene1_key =
+        world.create_gene_in_cell(cell1_key, &[0, 42, OUTPUT_NR, PROC_DESTROY_NR], &mut rng);
+    let gene2_key = world.create_gene_in_cell(
+        cell2_key,
+        &[0, HAS_INPUT_NR, 0, INPUT_NR, 0, HAS_INPUT_NR],
+        &mut rng,
+    );
+    world.create_processor(cell1_key, gene1_key);
+    world.execute_amount(4, &mut rng);
+    let processor2_key = world.create_processor(cell2_key, gene2_key);
+
+    world.execute_amount(6, &mut rng);
+
+    let processor2 = &world.processors[processor2_key];
+    assert_eq!(processor2.stack, [TRUE, 42, FALSE]);
+    assert_eq!(processor2.failures, 0);
+}
+
+#[test]
+fn test_connection_input_full() {
+    let mut world = connected_world();
+    world.config.max_connection_input = 1;
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell1_key = world.cell_at((1, 1)).unwrap();
+    let cell2_key = world.cell_at((2, 1)).unwrap();
+    let gene1_key = world.create_gene_in_cell(
+        cell1_key,
+        &[0, 42, OUTPUT_NR, 0, 43, OUTPUT_NR, PROC_DESTROY_NR],
+        &mut rng,
+    );
+    let processor1_key = world.create_processor(cell1_key, gene1_key);
+    world.execute_amount(6, &mut rng);
+    // the second value is dropped
+    assert_eq!(world.processors[processor1_key].failures, 1);
+
+    let gene2_key = world.create_gene_in_cell(cell2_key, &[0, INPUT_NR, 0, HAS_INPUT_NR], &mut rng);
+    let processor2_key = world.create_processor(cell2_key, gene2_key);
+    world.execute_amount(4, &mut rng);
+
+    assert_eq!(world.processors[processor2_key].stack, [42, FALSE]);
+}
+
+#[test]
+fn test_close_gene() {
+    let mut world = connected_world();
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell1_key = world.cell_at((1, 1)).unwrap();
+    let cell2_key = world.cell_at((2, 1)).unwrap();
+    // the receiving side closes itself for genes
+    let gene_key =
+        world.create_gene_in_cell(cell2_key, &[0, CLOSE_GENE_NR, 0, IS_OPEN_GENE_NR], &mut rng);
+    let processor_key = world.create_processor(cell2_key, gene_key);
+    world.execute_amount(4, &mut rng);
+    assert_eq!(world.processors[processor_key].stack, [FALSE]);
+    world.processors.remove(processor_key);
+
+    let gene1_key = world.create_gene_in_cell(cell1_key, &[3, 4, ADD_NR], &mut rng);
+    let gene2_key =
+        world.create_gene_in_cell(cell1_key, &[5, 3, LOOKUP_NR, 1, MOVE_GENE_NR], &mut rng);
+    let processor_key = world.create_processor(cell1_key, gene2_key);
+
+    world.execute_amount(5, &mut rng);
+
+    assert_eq!(world.processors[processor_key].failures, 1);
+    assert!(world.entities.cells[cell1_key].has_gene(gene1_key));
+    assert!(!world.entities.cells[cell2_key].has_gene(gene1_key));
+}
+
+#[test]
+fn test_open_chem() {
+    let mut world = connected_world();
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell_key = world.cell_at((1, 1)).unwrap();
+    let gene_key = world.create_gene_in_cell(
+        cell_key,
+        &[
+            0,
+            CLOSE_CHEM_NR,
+            0,
+            IS_OPEN_CHEM_NR,
+            0,
+            OPEN_CHEM_NR,
+            0,
+            IS_OPEN_CHEM_NR,
+            0,
+            OPEN_GENE_NR,
+            0,
+            IS_OPEN_GENE_NR,
+        ],
+        &mut rng,
+    );
+    let processor_key = world.create_processor(cell_key, gene_key);
+
+    world.execute_amount(12, &mut rng);
+
+    let processor = &world.processors[processor_key];
+    assert_eq!(processor.stack, [FALSE, TRUE, TRUE]);
+    assert_eq!(processor.failures, 0);
+}
+
+#[test]
+fn test_gene_gate_on_catalyst() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell_key = world.create_cell().unwrap();
+    world.add_organelle(cell_key, 0x010000, respiration_catalyst(&[3], &[3]));
+    let gene_key = world.create_gene_in_cell(
+        cell_key,
+        &[0, IS_OPEN_GENE_NR, 0, IS_OPEN_CHEM_NR],
+        &mut rng,
+    );
+    let processor_key = world.create_processor(cell_key, gene_key);
+
+    world.execute_amount(4, &mut rng);
+
+    let processor = &world.processors[processor_key];
+    // catalysts cannot be opened for genes
+    assert_eq!(processor.stack, [TRUE]);
+    assert_eq!(processor.failures, 1);
+}
+
+// chem space coordinates close to G
+const CHEM_G: u32 = 0x300102;
+
+#[test]
+fn test_ingest() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        transfer_amount: 2,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell_key = world.create_cell_at((1, 1)).unwrap();
+    world.add_organelle(
+        cell_key,
+        0x010000,
+        Organelle::Environment(Environment::new()),
+    );
+    world.set_environment_amount((1, 1), Molecule::G, 3);
+    let gene_key = world.create_gene_in_cell(cell_key, &[0, CHEM_G, INGEST_NR], &mut rng);
+    let processor_key = world.create_processor(cell_key, gene_key);
+
+    world.execute_amount(3, &mut rng);
+    assert_eq!(world.molecule_amount(cell_key, Molecule::G), 2);
+    assert_eq!(world.environment_amount((1, 1), Molecule::G), 1);
+
+    // not enough left in the environment
+    world.execute_amount(3, &mut rng);
+    assert_eq!(world.molecule_amount(cell_key, Molecule::G), 2);
+    assert_eq!(world.environment_amount((1, 1), Molecule::G), 1);
+    assert_eq!(world.processors[processor_key].failures, 1);
+}
+
+#[test]
+fn test_ingest_closed() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell_key = world.create_cell_at((1, 1)).unwrap();
+    world.add_organelle(
+        cell_key,
+        0x010000,
+        Organelle::Environment(Environment { chem_open: false }),
+    );
+    world.set_environment_amount((1, 1), Molecule::G, 3);
+    let gene_key = world.create_gene_in_cell(cell_key, &[0, CHEM_G, INGEST_NR], &mut rng);
+    let processor_key = world.create_processor(cell_key, gene_key);
+
+    world.execute_amount(3, &mut rng);
+
+    assert_eq!(world.molecule_amount(cell_key, Molecule::G), 0);
+    assert_eq!(world.environment_amount((1, 1), Molecule::G), 3);
+    assert_eq!(world.processors[processor_key].failures, 1);
+}
+
+#[test]
+fn test_expel() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell_key = world.create_cell_at((1, 1)).unwrap();
+    world.add_organelle(
+        cell_key,
+        0x010000,
+        Organelle::Environment(Environment::new()),
+    );
+    world.set_molecule_amount(cell_key, Molecule::G, 1);
+    let gene_key = world.create_gene_in_cell(cell_key, &[0, CHEM_G, EXPEL_NR], &mut rng);
+    let processor_key = world.create_processor(cell_key, gene_key);
+
+    world.execute_amount(6, &mut rng);
+
+    assert_eq!(world.molecule_amount(cell_key, Molecule::G), 0);
+    assert_eq!(world.environment_amount((1, 1), Molecule::G), 1);
+    // the second time there is nothing left to expel
+    assert_eq!(world.processors[processor_key].failures, 1);
+}
+
+#[test]
+fn test_expel_through_connection() {
+    let mut world = connected_world();
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell1_key = world.cell_at((1, 1)).unwrap();
+    let cell2_key = world.cell_at((2, 1)).unwrap();
+    world.set_molecule_amount(cell1_key, Molecule::G, 2);
+    let gene_key = world.create_gene_in_cell(cell1_key, &[0, CHEM_G, EXPEL_NR], &mut rng);
+    let processor_key = world.create_processor(cell1_key, gene_key);
+
+    world.execute_amount(3, &mut rng);
+    assert_eq!(world.molecule_amount(cell1_key, Molecule::G), 1);
+    assert_eq!(world.molecule_amount(cell2_key, Molecule::G), 1);
+
+    // the other side closes for chemicals
+    world.entities.cells[cell2_key].organelles[0].set_open(Gate::Chem, false);
+    world.execute_amount(3, &mut rng);
+    assert_eq!(world.molecule_amount(cell1_key, Molecule::G), 1);
+    assert_eq!(world.molecule_amount(cell2_key, Molecule::G), 1);
+    assert_eq!(world.processors[processor_key].failures, 1);
+}
+
+#[test]
+fn test_ingest_through_connection() {
+    let mut world = connected_world();
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell1_key = world.cell_at((1, 1)).unwrap();
+    let gene_key = world.create_gene_in_cell(cell1_key, &[0, CHEM_G, INGEST_NR], &mut rng);
+    let processor_key = world.create_processor(cell1_key, gene_key);
+
+    world.execute_amount(3, &mut rng);
+
+    // ingest only works for environment organelles
+    assert_eq!(world.processors[processor_key].failures, 1);
+}
+
+#[test]
+fn test_diffusion() {
+    let config = Config {
+        width: 5,
+        height: 1,
+        topology: Topology::Bounded,
+        diffusion_rates: enum_map! {
+            Molecule::G => 0.5,
+            _ => 0.0,
+        },
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    world.set_environment_amount((0, 0), Molecule::G, 1000);
+    world.set_environment_amount((4, 0), Molecule::O, 1000);
+
+    world.execute_amount(10, &mut rng);
+
+    let amounts: Vec<i64> = (0..5)
+        .map(|x| world.environment_amount((x, 0), Molecule::G))
+        .collect();
+    assert_eq!(amounts.iter().sum::<i64>(), 1000);
+    // a gradient away from the source
+    assert!(amounts.windows(2).all(|pair| pair[0] > pair[1]));
+    // O does not diffuse
+    assert_eq!(world.environment_amount((4, 0), Molecule::O), 1000);
+    assert_eq!(world.environment_amount((3, 0), Molecule::O), 0);
+}
+
+fn photosynthesis_catalyst() -> Organelle {
+    Organelle::Catalyst(Catalyst::new(
+        reaction::photosynthesis(),
+        Box::new(Fixed::new(&[1], &[1])),
+    ))
+}
+
+#[test]
+fn test_photosynthesis_light_gradient() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        width: 2,
+        height: 1,
+        topology: Topology::Bounded,
+        light: Light::Gradient {
+            west: 0.0,
+            east: 1.0,
+        },
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let mut cell_keys = vec![];
+    for x in 0..2 {
+        let cell_key = world.create_cell_at((x, 0)).unwrap();
+        world.set_molecule_amount(cell_key, Molecule::X, 60);
+        world.set_molecule_amount(cell_key, Molecule::W, 60);
+        world.add_organelle(cell_key, 0x010000, photosynthesis_catalyst());
+        // answer the problem correctly
+        let gene_key = world.create_gene_in_cell(cell_key, &[0, 0, INPUT_NR, OUTPUT_NR], &mut rng);
+        world.create_processor(cell_key, gene_key);
+        cell_keys.push(cell_key);
+    }
+
+    world.execute_amount(40, &mut rng);
+
+    // in the dark nothing happens
+    assert_eq!(world.molecule_amount(cell_keys[0], Molecule::G), 0);
+    assert_eq!(world.molecule_amount(cell_keys[1], Molecule::G), 10);
+}
+
+#[test]
+fn test_photosynthesis_day_night() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        light: Light::DayNight {
+            day: 4,
+            night: 4,
+            light: 1.0,
+        },
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell_key = world.create_cell().unwrap();
+    world.set_molecule_amount(cell_key, Molecule::X, 60);
+    world.set_molecule_amount(cell_key, Molecule::W, 60);
+    world.add_organelle(cell_key, 0x010000, photosynthesis_catalyst());
+    let gene_key = world.create_gene_in_cell(cell_key, &[0, 0, INPUT_NR, OUTPUT_NR], &mut rng);
+    world.create_processor(cell_key, gene_key);
+
+    // output happens on ticks 3 and 7
+    world.execute_amount(8, &mut rng);
+
+    assert_eq!(world.tick(), 8);
+    assert_eq!(world.molecule_amount(cell_key, Molecule::G), 1);
+}
+
+#[test]
+fn test_emit_smell() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        width: 3,
+        height: 1,
+        topology: Topology::Bounded,
+        scent_channels: vec![
+            ScentChannel {
+                diffusion: 0.0,
+                decay: 0.0,
+            },
+            ScentChannel {
+                diffusion: 0.0,
+                decay: 0.5,
+            },
+        ],
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell1_key = world.create_cell_at((0, 0)).unwrap();
+    let cell2_key = world.create_cell_at((1, 0)).unwrap();
+    // channel 3 wraps around to channel 1
+    let gene1_key =
+        world.create_gene_in_cell(cell1_key, &[3, 100, EMIT_NR, PROC_DESTROY_NR], &mut rng);
+    world.create_processor(cell1_key, gene1_key);
+
+    world.execute_amount(4, &mut rng);
+    assert_eq!(world.scent_amount(1, (0, 0)), 25.0);
+    assert_eq!(world.scent_amount(0, (0, 0)), 0.0);
+
+    // smell to the west, then east
+    let gene2_key =
+        world.create_gene_in_cell(cell2_key, &[1, 3, SMELL_NR, 1, 1, SMELL_NR], &mut rng);
+    let processor_key = world.create_processor(cell2_key, gene2_key);
+    world.execute_amount(6, &mut rng);
+
+    let processor = &world.processors[processor_key];
+    assert_eq!(processor.stack, [6, 0]);
+    assert_eq!(processor.failures, 0);
+}
+
+#[test]
+fn test_smell_beyond_edge() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        width: 3,
+        height: 1,
+        topology: Topology::Bounded,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell_key = world.create_cell_at((0, 0)).unwrap();
+    let gene_key = world.create_gene_in_cell(cell_key, &[0, 3, SMELL_NR], &mut rng);
+    let processor_key = world.create_processor(cell_key, gene_key);
+
+    world.execute_amount(3, &mut rng);
+
+    let processor = &world.processors[processor_key];
+    assert_eq!(processor.stack, []);
+    assert_eq!(processor.failures, 1);
+}
+
+#[test]
+fn test_signature() {
+    let mut world = World::new(Config::default());
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell_key = world.create_cell().unwrap();
+    assert_eq!(world.signature(cell_key), [0, 0, 0]);
+
+    let gene_keys: Vec<_> = (0..4)
+        .map(|i| world.create_gene_in_cell(cell_key, &[i], &mut rng))
+        .collect();
+    let ids: Vec<u32> = gene_keys
+        .iter()
+        .map(|&gene_key| world.entities.genes[gene_key].id)
+        .collect();
+    world.entities.genes[gene_keys[0]].activation = 1;
+    world.entities.genes[gene_keys[1]].activation = 5;
+    world.entities.genes[gene_keys[2]].activation = 0;
+    world.entities.genes[gene_keys[3]].activation = 3;
+
+    assert_eq!(world.signature(cell_key), [ids[1], ids[3], ids[0]]);
+}
+
+#[test]
+fn test_observe() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        width: 3,
+        height: 1,
+        topology: Topology::Bounded,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell1_key = world.create_cell_at((0, 0)).unwrap();
+    let cell2_key = world.create_cell_at((1, 0)).unwrap();
+    let gene1_key = world.create_gene_in_cell(cell2_key, &[1], &mut rng);
+    let gene2_key = world.create_gene_in_cell(cell2_key, &[2], &mut rng);
+    world.entities.genes[gene2_key].activation = 2;
+    let gene1_id = world.entities.genes[gene1_key].id;
+    let gene2_id = world.entities.genes[gene2_key].id;
+    // observe east, then west where there is no cell
+    let gene_key = world.create_gene_in_cell(cell1_key, &[1, OBSERVE_NR, 3, OBSERVE_NR], &mut rng);
+    let processor_key = world.create_processor(cell1_key, gene_key);
+
+    world.execute_amount(4, &mut rng);
+
+    let processor = &world.processors[processor_key];
+    assert_eq!(processor.stack, [gene2_id, gene1_id, 0, 0, 0, 0]);
+    assert_eq!(processor.failures, 0);
+    assert_eq!(
+        world.observe(cell1_key, Direction::East),
+        [gene2_id, gene1_id, 0]
+    );
+}
+
+#[test]
+fn test_excite_inhibit() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell_key = world.create_cell().unwrap();
+    let gene1_key = world.create_gene_in_cell(cell_key, &[3, 4, ADD_NR], &mut rng);
+    let gene2_key = world.create_gene_in_cell(
+        cell_key,
+        &[
+            5,
+            3,
+            LOOKUP_NR,
+            DUP_NR,
+            DUP_NR,
+            EXCITE_NR,
+            EXCITE_NR,
+            INHIBIT_NR,
+            3,
+            LOOKUP_NR,
+            ACTIVATION_NR,
+        ],
+        &mut rng,
+    );
+    let processor_key = world.create_processor(cell_key, gene2_key);
+
+    world.execute_amount(11, &mut rng);
+
+    let processor = &world.processors[processor_key];
+    assert_eq!(processor.stack, [5, 2]);
+    assert_eq!(processor.failures, 0);
+    assert_eq!(world.entities.genes[gene1_key].activation, 2);
+}
+
+#[test]
+fn test_activation_bounds() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        max_activation: 2,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell_key = world.create_cell().unwrap();
+    let gene1_key = world.create_gene_in_cell(cell_key, &[3, 4, ADD_NR], &mut rng);
+    let gene2_key = world.create_gene_in_cell(
+        cell_key,
+        &[
+            5, 3, LOOKUP_NR, DUP_NR, DUP_NR, EXCITE_NR, EXCITE_NR, EXCITE_NR,
+        ],
+        &mut rng,
+    );
+    let processor_key = world.create_processor(cell_key, gene2_key);
+    world.execute_amount(8, &mut rng);
+    assert_eq!(world.processors[processor_key].failures, 0);
+    assert_eq!(world.entities.genes[gene1_key].activation, 2);
+
+    let gene3_key = world.create_gene_in_cell(
+        cell_key,
+        &[
+            7, 3, LOOKUP_NR, DUP_NR, DUP_NR, INHIBIT_NR, INHIBIT_NR, INHIBIT_NR,
+        ],
+        &mut rng,
+    );
+    world.processors.clear();
+    let processor_key = world.create_processor(cell_key, gene3_key);
+    world.execute_amount(8, &mut rng);
+    assert_eq!(world.processors[processor_key].failures, 0);
+    assert_eq!(world.entities.genes[gene1_key].activation, 0);
+}
+
+#[test]
+fn test_activation_scheduling() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell_key = world.create_cell().unwrap();
+    let gene1_key = world.create_gene_in_cell(cell_key, &[1], &mut rng);
+    let gene2_key = world.create_gene_in_cell(cell_key, &[2], &mut rng);
+    let gene3_key = world.create_gene_in_cell(cell_key, &[3], &mut rng);
+    world.entities.genes[gene2_key].activation = 3;
+    world.entities.genes[gene3_key].activation = 0;
+    let processor1_key = world.create_processor(cell_key, gene1_key);
+    let processor2_key = world.create_processor(cell_key, gene2_key);
+    let processor3_key = world.create_processor(cell_key, gene3_key);
+
+    world.execute_amount(2, &mut rng);
+
+    assert_eq!(world.processors[processor1_key].stack.len(), 2);
+    assert_eq!(world.processors[processor2_key].stack.len(), 6);
+    // an inactive gene does not execute
+    assert_eq!(world.processors[processor3_key].stack.len(), 0);
+}
+
+#[test]
+fn test_send_receive() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        width: 3,
+        height: 1,
+        topology: Topology::Bounded,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell1_key = world.create_cell_at((0, 0)).unwrap();
+    let cell2_key = world.create_cell_at((1, 0)).unwrap();
+    // messages are not affected by the barrier
+    world.entities.cells[cell2_key].set_barrier(10);
+    let gene1_key = world.create_gene_in_cell(
+        cell1_key,
+        &[10, 1, SEND_NR, 20, 1, SEND_NR, PROC_DESTROY_NR],
+        &mut rng,
+    );
+    let processor1_key = world.create_processor(cell1_key, gene1_key);
+    world.execute_amount(7, &mut rng);
+    assert_eq!(world.entities.cells[cell2_key].messages, [10, 20]);
+    assert!(!world.processors.contains_key(processor1_key));
+
+    let gene2_key =
+        world.create_gene_in_cell(cell2_key, &[RECEIVE_NR, RECEIVE_NR, RECEIVE_NR], &mut rng);
+    let processor2_key = world.create_processor(cell2_key, gene2_key);
+    world.execute_amount(3, &mut rng);
+
+    let processor2 = &world.processors[processor2_key];
+    assert_eq!(processor2.stack, [10, 20]);
+    // the queue is empty the third time
+    assert_eq!(processor2.failures, 1);
+}
+
+#[test]
+fn test_send_full() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        width: 3,
+        height: 1,
+        topology: Topology::Bounded,
+        max_messages: 1,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell1_key = world.create_cell_at((0, 0)).unwrap();
+    let cell2_key = world.create_cell_at((1, 0)).unwrap();
+    let gene_key = world.create_gene_in_cell(
+        cell1_key,
+        &[10, 1, SEND_NR, 20, 1, SEND_NR, 30, 3, SEND_NR],
+        &mut rng,
+    );
+    let processor_key = world.create_processor(cell1_key, gene_key);
+
+    world.execute_amount(9, &mut rng);
+
+    assert_eq!(world.entities.cells[cell2_key].messages, [10]);
+    // the queue is full, and there is no cell to the west
+    assert_eq!(world.processors[processor_key].failures, 2);
+}
+
+#[test]
+fn test_raise_lower_barrier() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        initial_wall: 25,
+        barrier_step: 10,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell_key = world.create_cell().unwrap();
+    let gene_key = world.create_gene_in_cell(
+        cell_key,
+        &[
+            RAISE_BARRIER_NR,
+            RAISE_BARRIER_NR,
+            RAISE_BARRIER_NR,
+            LOWER_BARRIER_NR,
+        ],
+        &mut rng,
+    );
+    let processor_key = world.create_processor(cell_key, gene_key);
+
+    world.execute_amount(3, &mut rng);
+    assert_eq!(world.entities.cells[cell_key].barrier(), 20);
+    assert_eq!(world.molecule_amount(cell_key, Molecule::B), 5);
+    // not enough B left to raise the barrier further
+    assert_eq!(world.processors[processor_key].failures, 1);
+
+    world.execute_amount(1, &mut rng);
+    assert_eq!(world.entities.cells[cell_key].barrier(), 10);
+    assert_eq!(world.molecule_amount(cell_key, Molecule::B), 15);
+}
+
+#[test]
+fn test_raise_barrier_keeps_wall() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        initial_wall: 20,
+        barrier_step: 10,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell_key = world.create_cell().unwrap();
+    let gene_key =
+        world.create_gene_in_cell(cell_key, &[RAISE_BARRIER_NR, RAISE_BARRIER_NR], &mut rng);
+    let processor_key = world.create_processor(cell_key, gene_key);
+
+    world.execute_amount(2, &mut rng);
+
+    // raising the barrier again would have brought the wall down
+    assert!(world.entities.cells.contains_key(cell_key));
+    assert_eq!(world.processors[processor_key].failures, 1);
+    assert_eq!(world.entities.cells[cell_key].barrier(), 10);
+    assert_eq!(world.molecule_amount(cell_key, Molecule::B), 10);
+}
+
+#[test]
+fn test_lower_barrier_full_pool() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        barrier_step: 10,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell_key = world.create_cell().unwrap();
+    world.set_molecule_amount(cell_key, Molecule::B, 995);
+    world.entities.cells[cell_key].set_barrier(10);
+    let gene_key =
+        world.create_gene_in_cell(cell_key, &[LOWER_BARRIER_NR, LOWER_BARRIER_NR], &mut rng);
+    let processor_key = world.create_processor(cell_key, gene_key);
+
+    world.execute_amount(2, &mut rng);
+
+    // only what fits in the pool is lowered, the rest stays in the barrier
+    assert_eq!(world.molecule_amount(cell_key, Molecule::B), 1000);
+    assert_eq!(world.entities.cells[cell_key].barrier(), 5);
+    assert_eq!(world.processors[processor_key].failures, 1);
+}
+
+#[test]
+fn test_lower_barrier_down() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell_key = world.create_cell().unwrap();
+    let gene_key = world.create_gene_in_cell(cell_key, &[LOWER_BARRIER_NR], &mut rng);
+    let processor_key = world.create_processor(cell_key, gene_key);
+
+    world.execute_amount(1, &mut rng);
+
+    assert_eq!(world.processors[processor_key].failures, 1);
+    assert_eq!(world.entities.cells[cell_key].barrier(), 0);
+}
+
+#[test]
+fn test_attack() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        width: 3,
+        height: 1,
+        topology: Topology::Bounded,
+        attack_cost: 5,
+        attack_strength: 10,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell1_key = world.create_cell_at((0, 0)).unwrap();
+    let cell2_key = world.create_cell_at((1, 0)).unwrap();
+    world.connect(cell1_key, Direction::East);
+    world.entities.cells[cell2_key].set_barrier(15);
+    world.set_molecule_amount(cell1_key, Molecule::A, 10);
+    world.set_molecule_amount(cell1_key, Molecule::G, 1);
+    let gene_key = world.create_gene_in_cell(
+        cell1_key,
+        &[0x0, 0x300000, EXPEL_NR, 1, ATTACK_NR],
+        &mut rng,
+    );
+    let processor_key = world.create_processor(cell1_key, gene_key);
+
+    world.execute_amount(5, &mut rng);
+    // the barrier blocks the transfer
+    assert_eq!(world.molecule_amount(cell2_key, Molecule::G), 0);
+    assert_eq!(world.entities.cells[cell2_key].barrier(), 5);
+    assert_eq!(world.molecule_amount(cell1_key, Molecule::A), 5);
+
+    world.execute_amount(5, &mut rng);
+    assert_eq!(world.entities.cells[cell2_key].barrier(), 0);
+    assert_eq!(world.molecule_amount(cell1_key, Molecule::A), 0);
+    // the barrier is down, so the transfer goes through
+    world.execute_amount(3, &mut rng);
+    assert_eq!(world.molecule_amount(cell2_key, Molecule::G), 1);
+
+    // no more ATP to attack with
+    world.execute_amount(2, &mut rng);
+    assert_eq!(world.processors[processor_key].failures, 3);
+}
+
+#[test]
+fn test_attack_no_neighbor() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell_key = world.create_cell().unwrap();
+    world.set_molecule_amount(cell_key, Molecule::A, 100);
+    let gene_key = world.create_gene_in_cell(cell_key, &[1, ATTACK_NR], &mut rng);
+    let processor_key = world.create_processor(cell_key, gene_key);
+
+    world.execute_amount(2, &mut rng);
+
+    assert_eq!(world.processors[processor_key].failures, 1);
+    assert_eq!(world.molecule_amount(cell_key, Molecule::A), 100);
+}
+
+#[test]
+fn test_barrier_returns_to_environment() {
+    let config = Config {
+        initial_wall: 5,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell_key = world.create_cell_at((0, 0)).unwrap();
+    world.entities.cells[cell_key].set_barrier(7);
+
+    world.kill_cell(cell_key);
+
+    assert_eq!(world.environment_amount((0, 0), Molecule::B), 12);
+}
+
+#[test]
+fn test_chem_amount() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        initial_wall: 30,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell_key = world.create_cell_at((0, 0)).unwrap();
+    world.set_molecule_amount(cell_key, Molecule::G, 7);
+    world.set_environment_amount((0, 0), Molecule::G, 12);
+    world.set_environment_amount((1, 0), Molecule::G, 100);
+    let gene_key = world.create_gene_in_cell(
+        cell_key,
+        &[
+            CHEM_G,
+            CHEM_AMOUNT_NR,
+            WALL_AMOUNT_NR,
+            CHEM_G,
+            ENV_AMOUNT_NR,
+            0x400000,
+            CHEM_AMOUNT_NR,
+        ],
+        &mut rng,
+    );
+    let processor_key = world.create_processor(cell_key, gene_key);
+
+    world.execute_amount(7, &mut rng);
+
+    let processor = &world.processors[processor_key];
+    assert_eq!(processor.stack, [7, 30, 12, 0]);
+    assert_eq!(processor.failures, 0);
+}
+
+#[test]
+fn test_sense_cell() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        width: 10,
+        height: 10,
+        topology: Topology::Bounded,
+        sense_cost: 0,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell_key = world.create_cell_at((5, 5)).unwrap();
+    world.create_cell_at((3, 4)).unwrap();
+    world.create_cell_at((9, 9)).unwrap();
+    // nothing within 2, the nearest cell within 3
+    let gene_key =
+        world.create_gene_in_cell(cell_key, &[2, SENSE_CELL_NR, 3, SENSE_CELL_NR], &mut rng);
+    let processor_key = world.create_processor(cell_key, gene_key);
+
+    world.execute_amount(4, &mut rng);
+
+    let processor = &world.processors[processor_key];
+    assert_eq!(processor.stack, [-2i32 as u32, -1i32 as u32]);
+    assert_eq!(processor.failures, 1);
+}
+
+#[test]
+fn test_sense_chem() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        width: 10,
+        height: 10,
+        topology: Topology::Toroidal,
+        sense_cost: 0,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell_key = world.create_cell_at((0, 0)).unwrap();
+    world.set_environment_amount((0, 0), Molecule::G, 5);
+    world.set_environment_amount((0, 9), Molecule::G, 20);
+    world.set_environment_amount((2, 0), Molecule::G, 30);
+    world.set_environment_amount((5, 5), Molecule::G, 100);
+    let gene_key = world.create_gene_in_cell(
+        cell_key,
+        &[
+            CHEM_G,
+            0,
+            SENSE_CHEM_NR,
+            CHEM_G,
+            1,
+            SENSE_CHEM_NR,
+            CHEM_G,
+            2,
+            SENSE_CHEM_NR,
+        ],
+        &mut rng,
+    );
+    let processor_key = world.create_processor(cell_key, gene_key);
+
+    world.execute_amount(9, &mut rng);
+
+    let processor = &world.processors[processor_key];
+    assert_eq!(processor.stack, [0, 0, 5, 0, -1i32 as u32, 20, 2, 0, 30]);
+    assert_eq!(processor.failures, 0);
+}
+
+#[test]
+fn test_sense_cost() {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        sense_cost: 2,
+        max_sense_radius: 3,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let cell_key = world.create_cell().unwrap();
+    world.create_cell().unwrap();
+    world.set_molecule_amount(cell_key, Molecule::A, 30);
+    // the radius is capped at 3
+    let gene_key = world.create_gene_in_cell(cell_key, &[100, SENSE_CELL_NR], &mut rng);
+    let processor_key = world.create_processor(cell_key, gene_key);
+
+    world.execute_amount(2, &mut rng);
+    assert_eq!(world.molecule_amount(cell_key, Molecule::A), 12);
+    assert_eq!(world.processors[processor_key].stack.len(), 2);
+
+    // not enough ATP left, so the processor stalls
+    world.execute_amount(2, &mut rng);
+    assert_eq!(world.molecule_amount(cell_key, Molecule::A), 12);
+    assert_eq!(world.processors[processor_key].stack.len(), 3);
+}
+
+#[test]
+fn test_move_breaks_connection() {
+    let mut world = connected_world();
+    let cell1_key = world.cell_at((1, 1)).unwrap();
+    let cell2_key = world.cell_at((2, 1)).unwrap();
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    // move away and back again
+    let gene_key = world.create_gene_in_cell(cell1_key, &[2, MOVE_NR, 0, MOVE_NR], &mut rng);
+    let processor_key = world.create_processor(cell1_key, gene_key);
+
+    world.execute_amount(4, &mut rng);
+
+    assert_eq!(world.processors[processor_key].stack, [TRUE, TRUE]);
+    assert_eq!(world.cell_position(cell1_key), Some((1, 1)));
+    assert_eq!(
+        world.entities.connection_peer(cell1_key, Direction::East),
+        None
+    );
+    assert_eq!(
+        world.entities.connection_peer(cell2_key, Direction::West),
+        None
+    );
+}
+
+fn noise_world(noise_interval: u32, perturbation: Perturbation) -> (World, ProcessorKey) {
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        noise_interval,
+        perturbation,
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell_key = world.create_cell().unwrap();
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let gene_key = world.create_gene_in_cell(cell_key, &[1, 1, ADD_NR, 2, SUB_NR], &mut rng);
+    let processor_key = world.create_processor(cell_key, gene_key);
+    world.execute_amount(100, &mut rng);
+    (world, processor_key)
+}
+
+#[test]
+fn test_noise_disabled_by_default() {
+    assert_eq!(Config::default().noise_interval, 0);
+    let (world, processor_key) = noise_world(
+        0,
+        Perturbation {
+            amount: 255,
+            huge_amount: 255,
+            huge_probability: 1.0,
+        },
+    );
+    let p = &world.processors[processor_key];
+    assert_eq!(p.stack, [0; 20]);
+    assert_eq!(p.failures, 0);
+}
+
+#[test]
+fn test_noise_without_perturbation() {
+    // noise that does not perturb executes the same instructions
+    let (world, processor_key) = noise_world(
+        3,
+        Perturbation {
+            amount: 0,
+            huge_amount: 0,
+            huge_probability: 0.0,
+        },
+    );
+    let p = &world.processors[processor_key];
+    assert_eq!(p.stack, [0; 20]);
+    assert_eq!(p.failures, 0);
+}
+
+#[test]
+fn test_noise() {
+    // only every 3rd instruction is perturbed: that is the Add, which
+    // with this seed lands on its neighbour JF (0x010100) and skips 66
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        noise_interval: 3,
+        perturbation: Perturbation {
+            amount: 1,
+            huge_amount: 1,
+            huge_probability: 0.0,
+        },
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell_key = world.create_cell().unwrap();
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    let gene_key = world.create_gene_in_cell(cell_key, &[1, 1, ADD_NR, 66, 77], &mut rng);
+    let processor_key = world.create_processor(cell_key, gene_key);
+
+    world.execute_amount(4, &mut rng);
+
+    let p = &world.processors[processor_key];
+    assert_eq!(p.stack, [77]);
+    assert_eq!(p.failures, 0);
+}
+
+#[test]
+fn test_noise_call_mode() {
+    // only every 2nd instruction is perturbed: that is the call, which
+    // with this seed lands on the neighbouring gene at 0x010003 instead
+    // of the one at 3
+    let config = Config {
+        instruction_lookup: instruction_lookup(),
+        noise_interval: 2,
+        perturbation: Perturbation {
+            amount: 1,
+            huge_amount: 1,
+            huge_probability: 0.0,
+        },
+        ..Default::default()
+    };
+    let mut world = World::new(config);
+    let cell_key = world.create_cell().unwrap();
+    let mut rng = rand_pcg::Pcg32::from_seed(SEED);
+    world.create_gene_in_cell(cell_key, &[3, 4, ADD_NR], &mut rng);
+    let neighbour_key = world.create_gene_in_cell(cell_key, &[0x010003], &mut rng);
+    let gene_key = world.create_gene(&[5, CALL_MODE | 3]);
+    let processor_key = world.create_processor(cell_key, gene_key);
+
+    world.execute_amount(3, &mut rng);
+
+    let p = &world.processors[processor_key];
+    assert_eq!(p.gene_key(), neighbour_key);
+    assert_eq!(p.stack, [5, 0x010003]);
+    assert_eq!(p.failures, 0);
+}